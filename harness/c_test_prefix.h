@@ -1,4 +1,6 @@
 #include <inttypes.h>
+#include <stdarg.h>
+#include <stddef.h>
 #include <string.h>
 #include <stdio.h>
 #include <stdbool.h>
@@ -13,3 +15,4 @@ extern void (*WRITE)(WriteBuffer, char*, uint32_t);
 extern void (*FINISHED_VAL)(WriteBuffer);
 extern void (*FINISHED_FUNC)(WriteBuffer, WriteBuffer);
 
+extern bool CATCH_UNWIND(void (*)(void*), void*);
@@ -48,4 +48,73 @@ pub extern fn test_start(
 
         do_test();
     }
-}
\ No newline at end of file
+}
+
+// C has no way to catch an unwind, so C callers of functions that unwind
+// call them through here. Returns whether `body` unwound.
+#[no_mangle]
+pub unsafe extern "C" fn CATCH_UNWIND(
+    body: unsafe extern "C-unwind" fn(*mut ()),
+    env: *mut (),
+) -> bool {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| body(env))).is_err()
+}
+
+// When built as an executable (for the out-of-process runner) the harness
+// can't hand the WriteBuffers back to abi-checker directly, so instead it
+// logs every callback to the file named by its first argument, one event
+// per line:
+//
+// * `W <buffer> <hex bytes>` for WRITE
+// * `V <buffer>` for FINISHED_VAL
+// * `F <buffer> <buffer>` for FINISHED_FUNC
+//
+// where the buffers are numbered 0-3 in the order test_start takes them.
+// Every event is written out immediately, so if the test crashes or hangs
+// abi-checker can still tell how far it got.
+#[cfg(harness_exe)]
+mod exe {
+    use super::WriteBuffer;
+    use std::fs::File;
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    pub static EVENTS: Mutex<Option<File>> = Mutex::new(None);
+
+    fn emit(event: String) {
+        let mut events = EVENTS.lock().unwrap();
+        events.as_mut().unwrap().write_all(event.as_bytes()).unwrap();
+    }
+
+    pub unsafe extern fn write_field(output: WriteBuffer, input: *const u8, size: u32) {
+        let data = std::slice::from_raw_parts(input, size as usize);
+        let mut event = format!("W {} ", output.0 as usize);
+        for byte in data {
+            event.push_str(&format!("{:02x}", byte));
+        }
+        event.push('\n');
+        emit(event);
+    }
+    pub unsafe extern fn finished_val(output: WriteBuffer) {
+        emit(format!("V {}\n", output.0 as usize));
+    }
+    pub unsafe extern fn finished_func(output1: WriteBuffer, output2: WriteBuffer) {
+        emit(format!("F {} {}\n", output1.0 as usize, output2.0 as usize));
+    }
+}
+
+#[cfg(harness_exe)]
+fn main() {
+    let events_path = std::env::args().nth(1).expect("usage: harness <events-file>");
+    *exe::EVENTS.lock().unwrap() = Some(std::fs::File::create(events_path).unwrap());
+
+    test_start(
+        exe::write_field,
+        exe::finished_val,
+        exe::finished_func,
+        WriteBuffer(0 as *mut ()),
+        WriteBuffer(1 as *mut ()),
+        WriteBuffer(2 as *mut ()),
+        WriteBuffer(3 as *mut ()),
+    );
+}
@@ -45,4 +45,37 @@ impl FfiU128 {
             high: (val as u128 >> 64) as u64,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Reads `width` bits starting at bit `offset` of some bitfield storage.
+fn read_bits(storage: &[u8], offset: usize, width: usize) -> u64 {
+    let mut val = 0;
+    for i in 0..width {
+        let bit = offset + i;
+        if storage[bit / 8] >> (bit % 8) & 1 != 0 {
+            val |= 1 << i;
+        }
+    }
+    val
+}
+
+/// Writes the low `width` bits of `val` to bit `offset` of some bitfield storage.
+fn write_bits(storage: &mut [u8], offset: usize, width: usize, val: u64) {
+    for i in 0..width {
+        let bit = offset + i;
+        if val >> i & 1 != 0 {
+            storage[bit / 8] |= 1 << (bit % 8);
+        } else {
+            storage[bit / 8] &= !(1 << (bit % 8));
+        }
+    }
+}
+
+/// The contents of a NUL-terminated (possibly wide) string, including the NUL.
+unsafe fn str_with_nul<'a, T: Copy + Default + PartialEq>(ptr: *const T) -> &'a [T] {
+    let mut len = 0;
+    while *ptr.add(len) != T::default() {
+        len += 1;
+    }
+    core::slice::from_raw_parts(ptr, len + 1)
+}
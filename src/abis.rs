@@ -5,7 +5,9 @@ pub mod c;
 pub mod rust;
 
 use super::BuildError;
+use std::collections::HashMap;
 use std::io::Write;
+use std::ops::Range;
 use std::path::Path;
 
 pub use c::CcAbiImpl;
@@ -25,10 +27,13 @@ pub static ALL_CONVENTIONS: &[CallingConvention] = &[
     CallingConvention::Stdcall,
     CallingConvention::Fastcall,
     CallingConvention::Vectorcall,
+    CallingConvention::Win64,
+    CallingConvention::Sysv64,
+    CallingConvention::Thiscall,
+    CallingConvention::CUnwind,
+    CallingConvention::SystemUnwind,
     // Note sure if these have a purpose, so omitting them for now
     // CallingConvention::System,
-    // CallingConvention::Win64,
-    // CallingConvention::Sysv64,
     // CallingConvention::Aapcs,
 ];
 
@@ -69,8 +74,18 @@ pub trait AbiImpl {
         convention: CallingConvention,
     ) -> Result<(), BuildError>;
 
-    fn compile_callee(&self, src_path: &Path, lib_name: &str) -> Result<String, BuildError>;
-    fn compile_caller(&self, src_path: &Path, lib_name: &str) -> Result<String, BuildError>;
+    fn compile_callee(
+        &self,
+        test: &Test,
+        src_path: &Path,
+        lib_name: &str,
+    ) -> Result<String, BuildError>;
+    fn compile_caller(
+        &self,
+        test: &Test,
+        src_path: &Path,
+        lib_name: &str,
+    ) -> Result<String, BuildError>;
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -84,26 +99,73 @@ pub enum GenerateError {
 }
 
 /// A test, containing several subtests, each its own function
+///
+/// Test files are read as a `Test<RawVal>`, and become a `Test` once
+/// their Named values are resolved (see Test::resolve_types).
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-pub struct Test {
+pub struct Test<V = Val> {
     pub name: String,
-    pub funcs: Vec<Func>,
+    /// Named types that values can refer to (see RawVal::Named).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<TypeDecl>,
+    pub funcs: Vec<Func<V>>,
+    /// Always run this test with the out-of-process runner, because it's
+    /// known to crash the harness (which that reports as a crash instead of
+    /// taking the whole run down with it).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub out_of_process: bool,
+}
+
+/// A named type declared in a test's `types`, so that values can refer to
+/// it by name and only give their data (see RawVal::Named).
+///
+/// The types of fields and elements are given by dummy values, just like
+/// the inactive members of a union, and those can be Named too.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub enum TypeDecl {
+    /// A struct with fields of these types.
+    Struct(String, Vec<RawVal>),
+    /// A struct with a non-default layout (see Val::ReprStruct).
+    ReprStruct(String, StructRepr, Vec<RawVal>),
+    /// A union with members of these types.
+    Union(String, Vec<RawVal>),
+    /// An array of this many elements of this type, which can be 0 (making
+    /// it an EmptyArray). Only the test knows this name, C and Rust don't.
+    Array(String, RawVal, usize),
+    /// Another name for this type. Only the test knows this name.
+    Alias(String, RawVal),
 }
 
 /// A function's calling convention + signature which will
 /// be used to generate the caller+callee automatically.
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
-pub struct Func {
+#[serde(bound(deserialize = "V: serde::Deserialize<'de>"))]
+pub struct Func<V = Val> {
     pub name: String,
     pub conventions: Vec<CallingConvention>,
-    pub inputs: Vec<Val>,
-    pub output: Option<Val>,
+    pub inputs: Vec<V>,
+    /// Extra args passed through a C-style `...` after the inputs (which
+    /// must be non-empty). These are subject to the default argument
+    /// promotions (float to double, small ints and bools to int), but both
+    /// sides still report them as their original types.
+    ///
+    /// Only scalars (ints up to 64 bits, floats, bools, pointers) and Refs
+    /// can be passed this way.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variadic_inputs: Vec<V>,
+    pub output: Option<V>,
+    /// Instead of returning, the callee unwinds (a Rust panic, or from C a
+    /// foreign exception raised the same way a C++ `throw` would), and the
+    /// caller tries to catch it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub callee_unwinds: bool,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum CallingConvention {
     // These conventions are special ones that "desugar" to others
-    /// Sugar for "every possible convention"
+    /// Sugar for "every possible convention" (except the -unwind ones,
+    /// which are only interesting for functions that unwind)
     All,
     /// A complete opaque convention, the implementation must be manually
     /// written in the handwritten_impls directory.
@@ -114,6 +176,10 @@ pub enum CallingConvention {
     Cdecl,
     /// The platorm's default OS convention (usually C, but Windows is Weird).
     System,
+    /// C, but unwinding out of the function is allowed
+    CUnwind,
+    /// System, but unwinding out of the function is allowed
+    SystemUnwind,
 
     // These conventions are specific ones
     /// x64 windows C convention
@@ -132,56 +198,306 @@ pub enum CallingConvention {
     /// MSCV `__vectorcall`
     /// GCC/Clang `__attribute__((vectorcall))`
     Vectorcall,
+    /// x86 C++ member functions
+    /// MSVC `__thiscall`
+    /// GCC/Clang `__attribute__((thiscall))`
+    Thiscall,
 }
 
 /// A typed value.
-#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Clone, Debug, serde::Serialize)]
 pub enum Val {
     /// A Ref is passed-by-reference (is a pointer) but the
     /// pointee will be regarded as the real value that we check.
     ///
     /// If a Ref val is used as the return value for a function, it will
     /// implicitly introduce an outparam that the callee memcpy's to.
-    Ref(Box<Val>),
+    ///
+    /// The flavor picks which kind of Rust pointer the Rust side uses for it
+    /// (C doesn't care, it's always a `T*`). It can be omitted, in which case
+    /// it's the Default flavor.
+    Ref(
+        Box<Val>,
+        #[serde(default, skip_serializing_if = "RefFlavor::is_default")] RefFlavor,
+    ),
     /// Some integer
     Int(IntVal),
     /// Some float
     Float(FloatVal),
     /// A bool
     Bool(bool),
-    /// An array (homogeneous types, checked on construction)
+    /// A complex number, with its real and imaginary parts (which must
+    /// have the same type).
+    ///
+    /// C gets the real `float _Complex`/`double _Complex`, and Rust gets the
+    /// `#[repr(C)]` struct of the two parts that Rust users rely on being
+    /// layout-compatible (but which can be passed differently!). The two
+    /// parts are checked as two fields.
+    Complex(FloatVal, FloatVal),
+    /// An array, with a dummy value giving the type of its elements (which
+    /// all have that type, checked on construction).
     ///
     /// Arrays must be wrapped in a Ref to directly use them as args/returns
     /// when compiling to C. Rust is fine with passing them by-value, but of
     /// course this is pointless when the other half of the equation pukes.
     ///
-    /// FIXME: it isn't currently enforced that this is homogeneous, anything that needs
-    /// the type of the elements just grabs the type of element 0
+    /// Test files only give the elements, and the element type is worked out
+    /// when they're loaded (see Test::resolve_types), so it isn't written out.
+    /// It's illegal to have an array of length 0 there, because it's impossible
+    /// to attach a type to it (see EmptyArray, or declare the array's type).
+    Array(#[serde(skip_serializing)] Box<Val>, Vec<Val>),
+    /// An array of length 0, with a dummy value to give the elements a type.
     ///
-    /// FIXME: it's illegal to have an array of length 0, because it's impossible to
-    /// attach a type to it
-    Array(Vec<Val>),
+    /// This can only be a field of a struct (C: `T field[0]`, a GNU extension,
+    /// Rust: `[T; 0]`). It has no fields of its own, so it's only interesting
+    /// for how it affects the layout of the struct (its alignment).
+    EmptyArray(Box<Val>),
+    /// A C99 flexible array member, with a dummy value to give the elements
+    /// a type, and the elements that follow the struct.
+    ///
+    /// This can only be the last field of a struct that's passed by reference
+    /// (Ref) as an input, because the struct's size doesn't include it.
+    /// C gets `T field[]`, and Rust gets `[T; 0]` (the struct is generic over
+    /// that length, so that the side passing it in can allocate the elements).
+    FlexArray(Box<Val>, Vec<Val>),
     /// A named struct (heterogeneous type)
     ///
     /// Struct decls are implicitly derived from their usage as a value.
     /// If any two structs claim the same name but have different layouts,
     /// the ABI backends should notice this and return an error.
     Struct(String, Vec<Val>),
+    /// A named struct with a non-default layout (packed or overaligned).
+    ///
+    /// Otherwise this is just like a Struct (and shares its namespace).
+    /// Bitfields aren't supported in these.
+    ReprStruct(String, StructRepr, Vec<Val>),
     /// An opaque pointer
     ///
-    /// FIXME?: it's gross to just pick "u64" as the type here when ostensibly it would
-    /// be nice for this to be able to taget 32-bit platforms. But using usize doesn't really
-    /// make sense either because we're slurping these values out of a static config file!
-    /// I guess just truncating the pointer is "fine".
+    /// This is a u64 so that it can represent a pointer on any target, and
+    /// backends truncate it to the target's pointer width (see Target::truncate_ptr).
     Ptr(u64),
-    // TODO: unions. This is hard to do with the current design where
-    // types are implicit in their values. You could maybe hack it in
-    // by having dummy vals for all the different cases and then a
-    // "real" value for the variant that's actually used, but, kinda gross.
-    //
-    // TODO: simd vectors (they have special passing rules!)
-    //
-    // TODO: enums (enum classes?)
+    /// A named union, with the index of the member that is active.
+    ///
+    /// Types are implicit in values, so every member needs a value to give it
+    /// a type, but only the active member's value is actually used. The other
+    /// members are just dummies. Like structs, all unions with the same name
+    /// must match!
+    ///
+    /// Only the active member's fields get checked, along with the size of
+    /// the whole union (which the two sides must also agree on).
+    Union(String, usize, Vec<Val>),
+    /// A SIMD vector (they have special passing rules!)
+    ///
+    /// The whole vector is checked as a single field.
+    Vector(VectorVal),
+    /// A named C-style enum, with its repr, its variants (names and
+    /// discriminants), and the index of the variant that is the value.
+    ///
+    /// Like structs, all enums with the same name must match!
+    ///
+    /// C compilers don't all agree on how big an enum is, so the harness
+    /// reports the two sides disagreeing on the size specially.
+    Enum(String, EnumRepr, Vec<(String, i64)>, usize),
+    /// A named data-carrying enum, with its repr, its variants (names and
+    /// fields), and the index of the variant that is the value. The
+    /// discriminants count up from 0.
+    ///
+    /// Rust gets the real enum, and C gets the tagged union that RFC 2195
+    /// defines its layout to be: for repr(C), a struct of a C enum tag and a
+    /// union of a struct per variant, and for the other reprs, a union of a
+    /// struct per variant that each start with the tag.
+    ///
+    /// Like unions, every variant needs values to give its fields types, but
+    /// only the active variant's fields get checked, after the size of the
+    /// whole enum and its tag.
+    DataEnum(String, EnumRepr, Vec<(String, Vec<Val>)>, usize),
+    /// A pointer to a named function type with the given calling convention,
+    /// and the args that the side receiving the pointer will call it with.
+    ///
+    /// The side passing the pointer in defines a function for it that reports
+    /// the args it actually gets called with (into CALLEE_INPUTS, because it
+    /// runs while the callee is reporting its inputs). So the fields of this
+    /// value are the fields of those args. Like structs, all function pointers
+    /// with the same name must match!
+    ///
+    /// Function pointers can only be used directly as args, not returned
+    /// or nested inside other values.
+    FnPtr(String, CallingConvention, Vec<Val>),
+    /// A bitfield of the given width (in bits) with the given underlying
+    /// integer type and value.
+    ///
+    /// Bitfields can only be fields of a Struct. A width of 0 is an unnamed
+    /// zero-width bitfield, which only affects the layout (its value is ignored).
+    ///
+    /// Rust doesn't have bitfields, so the Rust backend lays out the storage
+    /// of each run of bitfields itself (following the SysV rules gcc and clang
+    /// use) and reads/writes the fields with generated accessors. Every field's
+    /// value is checked, followed by the raw bytes of each run's storage.
+    BitField(u32, IntVal),
+    /// A NUL-terminated string (`const char*`), which can't contain NULs.
+    ///
+    /// Like a Ref, the pointee is what gets checked: the bytes of the
+    /// string up to and including the NUL, not the address.
+    CStr(String),
+    /// A NUL-terminated wide string (`const wchar_t*`), which can't contain NULs.
+    ///
+    /// This is UTF-16 on Windows and UTF-32 everywhere else, and Rust gets a
+    /// pointer to u16 or u32 to match. Checked just like a CStr.
+    WideCStr(String),
+    /// An atomic integer (C: `_Atomic(T)`, Rust: `AtomicU32` and friends).
+    ///
+    /// These are checked just like the integer, but may be laid out or
+    /// passed differently than it.
+    Atomic(IntVal),
+    /// A value that the Rust side spells as the given type instead, which C
+    /// knows nothing about (e.g. `Option<core::num::NonZeroU32>` or `char` for
+    /// a `uint32_t`, or `Option<MyFnPtr>` for a function pointer).
+    ///
+    /// This is for the types that Rust guarantees to be FFI-safe thanks to
+    /// niches. The Rust side transmutes the value to the type, so it must be
+    /// the same size and be valid for the value's bits (and for zero, if it's
+    /// an output). Only ints, floats, bools, opaque pointers and function
+    /// pointers can be wrapped, and they're checked just like the value is.
+    RustType(String, Box<Val>),
+    /// A named `#[repr(transparent)]` newtype around a value, which Rust
+    /// passes where C just uses the value directly.
+    ///
+    /// This is checked just like the value is.
+    Transparent(String, Box<Val>),
+}
+
+/// A value as it's written in a test file, which is just like a Val except
+/// that it can refer to the types declared in the test.
+///
+/// These are resolved to Vals when the test is loaded (see
+/// Test::resolve_types), so the backends never see them.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub enum RawVal {
+    Ref(
+        Box<RawVal>,
+        #[serde(default, skip_serializing_if = "RefFlavor::is_default")] RefFlavor,
+    ),
+    Int(IntVal),
+    Float(FloatVal),
+    Bool(bool),
+    Complex(FloatVal, FloatVal),
+    /// Just the elements, so there must be at least one (see Val::Array).
+    Array(Vec<RawVal>),
+    EmptyArray(Box<RawVal>),
+    FlexArray(Box<RawVal>, Vec<RawVal>),
+    Struct(String, Vec<RawVal>),
+    ReprStruct(String, StructRepr, Vec<RawVal>),
+    Ptr(u64),
+    /// If the union is declared in the test's `types`, only the value of
+    /// the active member is given (or nothing, for its dummy).
+    Union(String, usize, Vec<RawVal>),
+    Vector(VectorVal),
+    Enum(String, EnumRepr, Vec<(String, i64)>, usize),
+    DataEnum(String, EnumRepr, Vec<(String, Vec<RawVal>)>, usize),
+    FnPtr(String, CallingConvention, Vec<RawVal>),
+    BitField(u32, IntVal),
+    CStr(String),
+    WideCStr(String),
+    Atomic(IntVal),
+    RustType(String, Box<RawVal>),
+    Transparent(String, Box<RawVal>),
+    /// A value of a type declared in the test's `types`, with just its data:
+    /// the fields of a struct, the elements of an array, the value of one
+    /// of the members of a union (the first one with the same type is made
+    /// active, a Union can pick one), or the value of an alias.
+    ///
+    /// With no data at all, it's the dummy values of the declaration.
+    Named(String, Vec<RawVal>),
+}
+
+/// How a ReprStruct's layout differs from the default.
+#[derive(Copy, Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum StructRepr {
+    /// The fields are aligned to at most N bytes, and so may be unaligned.
+    ///
+    /// C: `#pragma pack(N)` (`packed(1)` is `__attribute__((packed))`),
+    /// Rust: `#[repr(C, packed(N))]`
+    Packed(u32),
+    /// The whole struct is aligned to at least N bytes.
+    ///
+    /// C: `__attribute__((aligned(N)))` (or `__declspec(align(N))`),
+    /// Rust: `#[repr(C, align(N))]`
+    Aligned(u32),
+}
+
+/// The kind of Rust pointer a Ref is, all of which Rust guarantees to
+/// be passed just like a C pointer.
+///
+/// Anything other than the Default flavor can only be used directly as an
+/// input or output (not nested in another value, or as a variadic arg), and
+/// outputs can only be the flavors that mutably borrow the pointee.
+#[derive(Copy, Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum RefFlavor {
+    /// `&T` for inputs, `&mut T` for outputs, and `*mut T` anywhere else.
+    #[default]
+    Default,
+    /// `&T`
+    Ref,
+    /// `&mut T`
+    RefMut,
+    /// `Option<&T>`
+    OptionRef,
+    /// `Option<&mut T>`
+    OptionRefMut,
+    /// `core::ptr::NonNull<T>`
+    NonNull,
+    /// `Box<T>`
+    ///
+    /// The pointee isn't actually on the heap, so the callee has to forget it.
+    Box,
+    /// `Option<Box<T>>` (see Box)
+    OptionBox,
+    /// `*const T`
+    ConstPtr,
+    /// `*mut T`
+    MutPtr,
+}
+
+impl RefFlavor {
+    pub fn is_default(&self) -> bool {
+        *self == RefFlavor::Default
+    }
+
+    /// Whether this flavor can be used for an output (the outparam the
+    /// callee writes the value to).
+    pub fn can_be_output(&self) -> bool {
+        match self {
+            RefFlavor::Default
+            | RefFlavor::RefMut
+            | RefFlavor::OptionRefMut
+            | RefFlavor::NonNull
+            | RefFlavor::MutPtr => true,
+            RefFlavor::Ref
+            | RefFlavor::OptionRef
+            | RefFlavor::Box
+            | RefFlavor::OptionBox
+            | RefFlavor::ConstPtr => false,
+        }
+    }
+}
+
+/// The underlying type of an enum.
+///
+/// On the Rust side this is just the `#[repr]`. For C, anything other than
+/// `C` is emitted as an enum with a fixed underlying type, if the compiler
+/// supports those (C23, or as a clang extension). Otherwise it's left up to
+/// the compiler, which is exactly the kind of disagreement we want to catch.
+#[derive(Copy, Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum EnumRepr {
+    C,
+    u8,
+    u16,
+    u32,
+    u64,
+    i8,
+    i16,
+    i32,
+    i64,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -197,15 +513,400 @@ pub enum IntVal {
     c_uint32_t(u32),
     c_uint16_t(u16),
     c_uint8_t(u8),
-    // TODO: nastier platform-specific-layout c-types?
-    // i.e. c_int(i64), c_long(i32), char(i8), ...
+
+    // Platform-specific-layout c-types. The value is stored in the
+    // widest type this can be on any supported platform, and is
+    // truncated to the target's size when emitted.
+    c_char(i8),
+    c_schar(i8),
+    c_uchar(u8),
+    c_int(i32),
+    c_uint(u32),
+    c_long(i64),
+    c_ulong(u64),
+    c_size_t(u64),
+    c_ptrdiff_t(i64),
+    c_intptr_t(i64),
+    c_uintptr_t(u64),
+
+    /// Clang's `_BitInt(N)` (C23), which can be any width.
+    ///
+    /// The value is two's complement in little-endian 64-bit limbs, and is
+    /// truncated to `bits` when emitted (so missing limbs are zeros).
+    /// Rust has no equivalent, so it gets the native int where the width
+    /// matches one (i128 is the interesting one), and otherwise an opaque
+    /// byte array with the C type's size and alignment, like bindgen emits.
+    c_BitInt {
+        bits: u32,
+        signed: bool,
+        value: Vec<u64>,
+    },
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub enum FloatVal {
     c_double(f64),
     c_float(f32),
-    // Is there a reason to mess with `long double`? Surely not.
+
+    // Extended float types. The value is stored as a double, and both
+    // sides convert it to the real type (rounding to nearest, ties to even).
+    //
+    // Where rustc has no equivalent type (e.g. x87 `long double`, or f16/f128
+    // on stable), Rust gets a byte array of the same size and alignment
+    // instead, which is only good for passing by reference.
+    /// `long double`, whatever that is on the target (see Target::long_double)
+    c_long_double(f64),
+    /// `__float128` (only on x86), Rust's `f128`
+    c__float128(f64),
+    /// `_Float16`, Rust's `f16`
+    c_Float16(f64),
+    /// `__bf16` (needs gcc 13+ or clang), which Rust has no equivalent of
+    c__bf16(f64),
+}
+
+impl FloatVal {
+    /// The value as a double (exactly, for the extended types).
+    pub fn to_f64(&self) -> f64 {
+        match *self {
+            FloatVal::c_float(val) => val as f64,
+            FloatVal::c_double(val)
+            | FloatVal::c_long_double(val)
+            | FloatVal::c__float128(val)
+            | FloatVal::c_Float16(val)
+            | FloatVal::c__bf16(val) => val,
+        }
+    }
+}
+
+/// What `long double` actually is on a target.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LongDouble {
+    /// Just a double (Windows, 32-bit arm, Apple's aarch64)
+    F64,
+    /// The x87 80-bit format, padded out to 12 (x86) or 16 (x86_64) bytes
+    X87,
+    /// IEEE binary128 (most other 64-bit linux targets)
+    F128,
+}
+
+/// Converts a double to an IEEE-style binary format with the given number of
+/// exponent and (explicit) mantissa bits, rounding to nearest, ties to even.
+/// The result is in the low bits.
+pub fn f64_to_float_bits(val: f64, exp_bits: u32, mant_bits: u32) -> u128 {
+    let bits = val.to_bits();
+    let sign = (bits >> 63) as u128;
+    let exp = ((bits >> 52) & 0x7ff) as i64;
+    let frac = (bits & ((1 << 52) - 1)) as u128;
+    let max_exp = (1i64 << exp_bits) - 1;
+    let bias = (1i64 << (exp_bits - 1)) - 1;
+    let mant_mask = (1u128 << mant_bits) - 1;
+    let inf = (max_exp as u128) << mant_bits;
+
+    let magnitude = if exp == 0x7ff {
+        // Keep NaNs quiet NaNs
+        inf | if frac != 0 { 1 << (mant_bits - 1) } else { 0 }
+    } else if exp == 0 && frac == 0 {
+        0
+    } else {
+        // val = sig * 2^pow
+        let (sig, pow) = if exp == 0 {
+            (frac, -1074)
+        } else {
+            (frac | 1 << 52, exp - 1075)
+        };
+        let top_bit = 127 - sig.leading_zeros() as i64;
+        let new_exp = pow + top_bit + bias;
+        // How many low bits of sig don't fit (negative if they all do)
+        let shift = if new_exp <= 0 {
+            // Subnormal, where the value is m * 2^(1 - bias - mant_bits)
+            (1 - bias - mant_bits as i64) - pow
+        } else {
+            top_bit - mant_bits as i64
+        };
+        let m = if shift <= 0 {
+            sig << -shift
+        } else if shift >= 128 {
+            0
+        } else {
+            let kept = sig >> shift;
+            let rest = sig & ((1 << shift) - 1);
+            let half = 1 << (shift - 1);
+            if rest > half || (rest == half && kept & 1 == 1) {
+                kept + 1
+            } else {
+                kept
+            }
+        };
+        if new_exp <= 0 {
+            // Rounding up into the smallest normal carries into the exponent
+            m
+        } else {
+            let (m, new_exp) = if m >> (mant_bits + 1) != 0 {
+                (m >> 1, new_exp + 1)
+            } else {
+                (m, new_exp)
+            };
+            if new_exp >= max_exp {
+                inf
+            } else {
+                (new_exp as u128) << mant_bits | (m & mant_mask)
+            }
+        }
+    };
+    sign << (exp_bits + mant_bits) | magnitude
+}
+
+/// Converts a double to the x87 80-bit format (exactly), in the low bits.
+///
+/// This is binary128 with the integer bit made explicit, and the
+/// fraction cut down to 63 bits (which is still more than a double has).
+pub fn f64_to_x87_bits(val: f64) -> u128 {
+    let quad = f64_to_float_bits(val, 15, 112);
+    let sign_exp = quad >> 112;
+    let exp = sign_exp & 0x7fff;
+    let integer_bit = if exp != 0 { 1u128 << 63 } else { 0 };
+    let frac = (quad >> (112 - 63)) & ((1 << 63) - 1);
+    sign_exp << 64 | integer_bit | frac
+}
+
+/// The bytes of a _BitInt's value, little-endian, truncated to `bits`
+/// and then sign or zero extended to `size` bytes.
+pub fn bit_int_bytes(bits: u32, signed: bool, value: &[u64], size: u64) -> Vec<u8> {
+    let mut bytes = value
+        .iter()
+        .flat_map(|limb| limb.to_le_bytes())
+        .collect::<Vec<u8>>();
+    bytes.resize((size as usize).max(bytes.len()), 0);
+    let negative =
+        signed && bits > 0 && (bytes[(bits as usize - 1) / 8] >> ((bits - 1) % 8)) & 1 == 1;
+    for bit in bits as usize..bytes.len() * 8 {
+        let mask = 1 << (bit % 8);
+        if negative {
+            bytes[bit / 8] |= mask;
+        } else {
+            bytes[bit / 8] &= !mask;
+        }
+    }
+    bytes.truncate(size as usize);
+    bytes
+}
+
+/// SIMD vector types, with their lanes.
+///
+/// Only x86_64's vectors (from immintrin.h/core::arch::x86_64) for now.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub enum VectorVal {
+    c__m128([f32; 4]),
+    c__m128i([i64; 2]),
+    c__m256([f32; 8]),
+    c__m256d([f64; 4]),
+}
+
+impl IntVal {
+    /// The size of this int in bytes on the given target,
+    /// or None for 128-bit ints and _BitInts (whose layouts are a mess).
+    pub fn size(&self, target: &Target) -> Option<u64> {
+        use IntVal::*;
+        let ptr_size = target.pointer_width() as u64 / 8;
+        Some(match self {
+            c__int128(_) | c__uint128(_) | c_BitInt { .. } => return None,
+            c_int64_t(_) | c_uint64_t(_) => 8,
+            c_int32_t(_) | c_uint32_t(_) | c_int(_) | c_uint(_) => 4,
+            c_int16_t(_) | c_uint16_t(_) => 2,
+            c_int8_t(_) | c_uint8_t(_) | c_char(_) | c_schar(_) | c_uchar(_) => 1,
+            c_long(_) | c_ulong(_) if target.is_windows() => 4,
+            c_long(_) | c_ulong(_) | c_size_t(_) | c_ptrdiff_t(_) | c_intptr_t(_)
+            | c_uintptr_t(_) => ptr_size,
+        })
+    }
+
+    /// Whether this int is signed on the given target.
+    pub fn is_signed(&self, target: &Target) -> bool {
+        use IntVal::*;
+        match self {
+            c__int128(_) | c_int64_t(_) | c_int32_t(_) | c_int16_t(_) | c_int8_t(_)
+            | c_schar(_) | c_int(_) | c_long(_) | c_ptrdiff_t(_) | c_intptr_t(_) => true,
+            c__uint128(_) | c_uint64_t(_) | c_uint32_t(_) | c_uint16_t(_) | c_uint8_t(_)
+            | c_uchar(_) | c_uint(_) | c_ulong(_) | c_size_t(_) | c_uintptr_t(_) => false,
+            c_char(_) => target.char_is_signed(),
+            c_BitInt { signed, .. } => *signed,
+        }
+    }
+}
+
+impl VectorVal {
+    /// The target feature that must be enabled to pass this vector in
+    /// registers, if the baseline for the arch isn't enough.
+    pub fn target_feature(&self) -> Option<&'static str> {
+        match self {
+            VectorVal::c__m128(_) | VectorVal::c__m128i(_) => None,
+            VectorVal::c__m256(_) | VectorVal::c__m256d(_) => Some("avx"),
+        }
+    }
+}
+
+/// The platform the tests are built for (and run on).
+///
+/// This is the platform abi-checker itself was built for unless --target says
+/// otherwise, in which case the harness has to be run out-of-process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub triple: String,
+}
+
+impl Target {
+    pub fn new(triple: &str) -> Self {
+        Self {
+            triple: String::from(triple),
+        }
+    }
+    pub fn host() -> Self {
+        Self::new(crate::built_info::TARGET)
+    }
+    pub fn is_host(&self) -> bool {
+        self.triple == crate::built_info::TARGET
+    }
+    pub fn arch(&self) -> &str {
+        self.triple.split('-').next().unwrap_or("")
+    }
+    /// 32-bit x86
+    pub fn is_x86(&self) -> bool {
+        matches!(self.arch(), "i386" | "i586" | "i686")
+    }
+    pub fn is_x86_64(&self) -> bool {
+        self.arch() == "x86_64"
+    }
+    pub fn is_windows(&self) -> bool {
+        self.triple.contains("windows")
+    }
+    /// Whether plain `char` is signed on this target (the same rule
+    /// `core::ffi::c_char` uses).
+    pub fn char_is_signed(&self) -> bool {
+        let arch = self.arch();
+        let unsigned_arch = arch.starts_with("arm")
+            || arch.starts_with("thumb")
+            || arch.starts_with("aarch64")
+            || arch.starts_with("riscv")
+            || arch.starts_with("powerpc")
+            || arch == "s390x";
+        !unsigned_arch || self.is_windows() || self.triple.contains("apple")
+    }
+    /// What `long double` is on this target, if we know.
+    pub fn long_double(&self) -> Option<LongDouble> {
+        let arch = self.arch();
+        if self.is_windows()
+            || arch.starts_with("arm")
+            || arch.starts_with("thumb")
+            || (arch == "aarch64" && self.triple.contains("apple"))
+        {
+            Some(LongDouble::F64)
+        } else if self.is_x86() || self.is_x86_64() {
+            Some(LongDouble::X87)
+        } else if matches!(arch, "aarch64" | "riscv64gc" | "riscv64" | "s390x") {
+            Some(LongDouble::F128)
+        } else {
+            None
+        }
+    }
+    /// The size and alignment of `_BitInt(bits)` on this target, if we know.
+    pub fn bit_int_layout(&self, bits: u32) -> Option<(u64, u64)> {
+        let bits = bits as u64;
+        let small = match bits {
+            0..=8 => 1,
+            9..=16 => 2,
+            17..=32 => 4,
+            33..=64 => 8,
+            _ => 0,
+        };
+        if self.is_x86_64() {
+            // Past 64 bits, an array of 64-bit chunks
+            if small != 0 {
+                Some((small, small))
+            } else {
+                Some((bits.div_ceil(64) * 8, 8))
+            }
+        } else if self.arch() == "aarch64" {
+            // Past 64 bits, an array of 128-bit chunks
+            if small != 0 {
+                Some((small, small))
+            } else {
+                Some((bits.div_ceil(128) * 16, 16))
+            }
+        } else {
+            None
+        }
+    }
+    pub fn is_big_endian(&self) -> bool {
+        matches!(
+            self.arch(),
+            "s390x" | "powerpc" | "powerpc64" | "sparc" | "sparc64" | "mips" | "mips64"
+        )
+    }
+    pub fn pointer_width(&self) -> u32 {
+        if self.arch().contains("64") || self.arch() == "s390x" {
+            64
+        } else {
+            32
+        }
+    }
+    /// Whether the host can run this target's executables without an
+    /// emulator (i.e. it's the same arch, or 32-bit x86 on x86_64).
+    pub fn runs_on_host(&self) -> bool {
+        let host = Target::host();
+        self.arch() == host.arch() || (self.is_x86() && host.is_x86_64())
+    }
+    /// The qemu-user emulator to run this target's executables under,
+    /// if the host can't run them itself.
+    pub fn qemu_runner(&self) -> Option<String> {
+        if self.runs_on_host() {
+            return None;
+        }
+        let arch = self.arch();
+        let qemu_arch = if arch.starts_with("riscv64") {
+            "riscv64"
+        } else if arch.starts_with("riscv32") {
+            "riscv32"
+        } else if arch.starts_with("arm") || arch.starts_with("thumb") {
+            "arm"
+        } else if arch == "powerpc64le" {
+            "ppc64le"
+        } else if arch == "powerpc64" {
+            "ppc64"
+        } else {
+            arch
+        };
+        Some(format!("qemu-{qemu_arch}"))
+    }
+    /// The prefix of the GNU cross toolchain for this target
+    /// (e.g. "aarch64-linux-gnu" for "aarch64-unknown-linux-gnu"),
+    /// if it needs a cross toolchain at all.
+    pub fn gnu_prefix(&self) -> Option<String> {
+        if self.runs_on_host() {
+            return None;
+        }
+        let mut parts = self.triple.split('-').collect::<Vec<_>>();
+        if parts.len() == 4 {
+            // Drop the vendor
+            parts.remove(1);
+        }
+        let arch = self.arch();
+        parts[0] = if arch.starts_with("riscv64") {
+            "riscv64"
+        } else if arch.starts_with("arm") {
+            "arm"
+        } else {
+            arch
+        };
+        Some(parts.join("-"))
+    }
+    /// Chop off the parts of a Val::Ptr that don't fit in a pointer.
+    pub fn truncate_ptr(&self, addr: u64) -> u64 {
+        if self.pointer_width() == 32 {
+            addr as u32 as u64
+        } else {
+            addr
+        }
+    }
 }
 
 impl CallingConvention {
@@ -218,14 +919,28 @@ impl CallingConvention {
             CallingConvention::C => "c",
             CallingConvention::Cdecl => "cdecl",
             CallingConvention::System => "system",
+            CallingConvention::CUnwind => "c-unwind",
+            CallingConvention::SystemUnwind => "system-unwind",
             CallingConvention::Win64 => "win64",
             CallingConvention::Sysv64 => "sysv64",
             CallingConvention::Aapcs => "aapcs",
             CallingConvention::Stdcall => "stdcall",
             CallingConvention::Fastcall => "fastcall",
             CallingConvention::Vectorcall => "vectorcall",
+            CallingConvention::Thiscall => "thiscall",
         }
     }
+    /// Whether variadic functions can use this convention.
+    pub fn allows_variadics(&self) -> bool {
+        matches!(self, CallingConvention::C | CallingConvention::Cdecl)
+    }
+    /// Whether functions with this convention may unwind into their caller.
+    pub fn allows_unwinding(&self) -> bool {
+        matches!(
+            self,
+            CallingConvention::CUnwind | CallingConvention::SystemUnwind
+        )
+    }
     pub fn from_str(input: &str) -> Option<Self> {
         Some(match input {
             "all" => CallingConvention::All,
@@ -233,30 +948,791 @@ impl CallingConvention {
             "c" => CallingConvention::C,
             "cdecl" => CallingConvention::Cdecl,
             "system" => CallingConvention::System,
+            "c-unwind" => CallingConvention::CUnwind,
+            "system-unwind" => CallingConvention::SystemUnwind,
             "win64" => CallingConvention::Win64,
             "sysv64" => CallingConvention::Sysv64,
             "aapcs" => CallingConvention::Aapcs,
             "stdcall" => CallingConvention::Stdcall,
             "fastcall" => CallingConvention::Fastcall,
             "vectorcall" => CallingConvention::Vectorcall,
+            "thiscall" => CallingConvention::Thiscall,
             _ => return None,
         })
     }
 }
 
+impl Val {
+    /// Calls `f` on this value, and every value nested inside of it.
+    pub fn visit(&self, f: &mut dyn FnMut(&Val)) {
+        f(self);
+        match self {
+            Val::Ref(pointee, _)
+            | Val::EmptyArray(pointee)
+            | Val::RustType(_, pointee)
+            | Val::Transparent(_, pointee) => pointee.visit(f),
+            Val::FlexArray(elem_ty, vals) => {
+                elem_ty.visit(f);
+                for val in vals {
+                    val.visit(f);
+                }
+            }
+            Val::Array(_, vals)
+            | Val::Struct(_, vals)
+            | Val::ReprStruct(_, _, vals)
+            | Val::Union(_, _, vals)
+            | Val::FnPtr(_, _, vals) => {
+                for val in vals {
+                    val.visit(f);
+                }
+            }
+            Val::DataEnum(_, _, variants, _) => {
+                for (_, fields) in variants {
+                    for val in fields {
+                        val.visit(f);
+                    }
+                }
+            }
+            Val::Int(_)
+            | Val::Float(_)
+            | Val::Complex(..)
+            | Val::Bool(_)
+            | Val::Ptr(_)
+            | Val::Vector(_)
+            | Val::Enum(..)
+            | Val::BitField(..)
+            | Val::CStr(_)
+            | Val::WideCStr(_)
+            | Val::Atomic(_) => {}
+        }
+    }
+
+    /// Whether this value has the same type as `other` (ignoring the actual
+    /// values, and the number of elements of flexible array members).
+    pub fn same_type(&self, other: &Val) -> bool {
+        use std::mem::discriminant;
+        let all_same = |vals: &[Val], others: &[Val]| {
+            vals.len() == others.len()
+                && vals
+                    .iter()
+                    .zip(others)
+                    .all(|(val, other)| val.same_type(other))
+        };
+        let same_int = |int: &IntVal, other: &IntVal| match (int, other) {
+            (
+                IntVal::c_BitInt { bits, signed, .. },
+                IntVal::c_BitInt {
+                    bits: other_bits,
+                    signed: other_signed,
+                    ..
+                },
+            ) => bits == other_bits && signed == other_signed,
+            _ => discriminant(int) == discriminant(other),
+        };
+        match (self, other) {
+            (Val::Ref(pointee, flavor), Val::Ref(other, other_flavor)) => {
+                flavor == other_flavor && pointee.same_type(other)
+            }
+            (Val::Int(int), Val::Int(other)) | (Val::Atomic(int), Val::Atomic(other)) => {
+                same_int(int, other)
+            }
+            (Val::BitField(width, int), Val::BitField(other_width, other)) => {
+                width == other_width && same_int(int, other)
+            }
+            (Val::Float(float), Val::Float(other)) => discriminant(float) == discriminant(other),
+            (Val::Complex(re, _), Val::Complex(other, _)) => {
+                discriminant(re) == discriminant(other)
+            }
+            (Val::Vector(vector), Val::Vector(other)) => {
+                discriminant(vector) == discriminant(other)
+            }
+            (Val::Bool(_), Val::Bool(_))
+            | (Val::Ptr(_), Val::Ptr(_))
+            | (Val::CStr(_), Val::CStr(_))
+            | (Val::WideCStr(_), Val::WideCStr(_)) => true,
+            (Val::Array(elem_ty, vals), Val::Array(other, others)) => {
+                vals.len() == others.len() && elem_ty.same_type(other)
+            }
+            (Val::EmptyArray(elem_ty), Val::EmptyArray(other))
+            | (Val::FlexArray(elem_ty, _), Val::FlexArray(other, _)) => elem_ty.same_type(other),
+            (Val::Struct(name, fields), Val::Struct(other_name, others)) => {
+                name == other_name && all_same(fields, others)
+            }
+            (
+                Val::ReprStruct(name, repr, fields),
+                Val::ReprStruct(other_name, other_repr, others),
+            ) => name == other_name && repr == other_repr && all_same(fields, others),
+            (Val::Union(name, _, fields), Val::Union(other_name, _, others)) => {
+                name == other_name && all_same(fields, others)
+            }
+            (Val::Enum(name, repr, variants, _), Val::Enum(other_name, other_repr, others, _)) => {
+                name == other_name && repr == other_repr && variants == others
+            }
+            (
+                Val::DataEnum(name, repr, variants, _),
+                Val::DataEnum(other_name, other_repr, others, _),
+            ) => {
+                name == other_name
+                    && repr == other_repr
+                    && variants.len() == others.len()
+                    && variants
+                        .iter()
+                        .zip(others)
+                        .all(|((variant, fields), (other, others))| {
+                            variant == other && all_same(fields, others)
+                        })
+            }
+            (
+                Val::FnPtr(name, convention, args),
+                Val::FnPtr(other_name, other_convention, others),
+            ) => name == other_name && convention == other_convention && all_same(args, others),
+            (Val::RustType(ty, val), Val::RustType(other_ty, other)) => {
+                ty == other_ty && val.same_type(other)
+            }
+            (Val::Transparent(name, val), Val::Transparent(other_name, other)) => {
+                name == other_name && val.same_type(other)
+            }
+            _ => false,
+        }
+    }
+
+    /// This value with any RustType wrapper removed (which is all C sees).
+    pub fn without_rust_type(&self) -> &Val {
+        match self {
+            Val::RustType(_, val) => val.without_rust_type(),
+            val => val,
+        }
+    }
+
+    /// The value that each field WRITE'd for this value corresponds to,
+    /// in the same order that the backends report them.
+    ///
+    /// Pseudo-fields (like the size of a union) correspond to the
+    /// value they describe.
+    pub fn leaf_fields(&self) -> Vec<&Val> {
+        match self {
+            Val::Ref(pointee, _) | Val::RustType(_, pointee) | Val::Transparent(_, pointee) => {
+                pointee.leaf_fields()
+            }
+            Val::Array(_, vals) | Val::FnPtr(_, _, vals) | Val::FlexArray(_, vals) => {
+                vals.iter().flat_map(|val| val.leaf_fields()).collect()
+            }
+            Val::EmptyArray(_) => vec![],
+            Val::ReprStruct(_, _, vals) => vals.iter().flat_map(|val| val.leaf_fields()).collect(),
+            Val::Struct(_, vals) => {
+                let mut fields = vec![];
+                for (run, vals) in struct_field_runs(vals) {
+                    for val in vals {
+                        if !matches!(val, Val::BitField(0, _)) {
+                            fields.extend(val.leaf_fields());
+                        }
+                    }
+                    // The raw storage of a run of bitfields
+                    if run && vals.iter().any(|val| !matches!(val, Val::BitField(0, _))) {
+                        fields.push(self);
+                    }
+                }
+                fields
+            }
+            Val::Union(_, active, vals) => {
+                let mut fields = vec![self];
+                if let Some(val) = vals.get(*active) {
+                    fields.extend(val.leaf_fields());
+                }
+                fields
+            }
+            Val::DataEnum(_, _, variants, active) => {
+                let mut fields = vec![self, self];
+                if let Some((_, vals)) = variants.get(*active) {
+                    fields.extend(vals.iter().flat_map(|val| val.leaf_fields()));
+                }
+                fields
+            }
+            Val::Complex(..) => vec![self, self],
+            Val::Int(_)
+            | Val::Float(_)
+            | Val::Bool(_)
+            | Val::Ptr(_)
+            | Val::Vector(_)
+            | Val::Enum(..)
+            | Val::BitField(..)
+            | Val::CStr(_)
+            | Val::WideCStr(_)
+            | Val::Atomic(_) => vec![self],
+        }
+    }
+}
+
+/// Splits a struct's fields into runs of consecutive bitfields (true) and
+/// the other fields (false, one at a time).
+pub fn struct_field_runs(fields: &[Val]) -> Vec<(bool, &[Val])> {
+    let mut runs = vec![];
+    let mut start = 0;
+    while start < fields.len() {
+        let is_bitfield = |val: &Val| matches!(val, Val::BitField(..));
+        let len = if is_bitfield(&fields[start]) {
+            fields[start..]
+                .iter()
+                .take_while(|val| is_bitfield(val))
+                .count()
+        } else {
+            1
+        };
+        runs.push((
+            len > 1 || is_bitfield(&fields[start]),
+            &fields[start..start + len],
+        ));
+        start += len;
+    }
+    runs
+}
+
+/// Where everything in a struct with bitfields ends up.
+///
+/// This follows the SysV rules that gcc and clang use everywhere but Windows:
+/// a bitfield is placed right after the previous field, unless it would
+/// straddle a boundary of the size of its type, in which case it's bumped to
+/// the next one. Zero-width bitfields bump the next field to that boundary.
+#[derive(Debug)]
+pub struct BitFieldLayout {
+    /// The offset of every field, in bits.
+    pub offsets: Vec<u64>,
+    /// The size of every field's type, in bytes.
+    pub sizes: Vec<u64>,
+    /// The fields in each run of bitfields, and the bytes holding them.
+    /// Runs with only zero-width bitfields have no storage, and aren't included.
+    pub runs: Vec<(Range<usize>, Range<u64>)>,
+    /// Where the last field ends (rounded up to a byte), before the
+    /// trailing padding.
+    pub end: u64,
+    pub size: u64,
+    pub align: u64,
+}
+
+/// Computes the layout of a struct with bitfields (see [`BitFieldLayout`]).
+///
+/// Only structs of ints, floats, bools, pointers, and bitfields are
+/// supported, because those are the only things we know the layout of.
+pub fn bitfield_layout(fields: &[Val], target: &Target) -> Result<BitFieldLayout, String> {
+    if target.is_windows() {
+        return Err(
+            "bitfields are only supported with the SysV layout (not on Windows)".to_owned(),
+        );
+    }
+    let ptr_size = target.pointer_width() as u64 / 8;
+    let align_of = |size: u64| {
+        if target.is_x86() && size == 8 {
+            4
+        } else {
+            size
+        }
+    };
+    let align_up = |offset: u64, align: u64| offset.div_ceil(align) * align;
+
+    let mut layout = BitFieldLayout {
+        offsets: vec![],
+        sizes: vec![],
+        runs: vec![],
+        end: 0,
+        size: 0,
+        align: 1,
+    };
+    let mut bit_offset = 0;
+    for field in fields {
+        let size = match field {
+            Val::Int(int_val) | Val::BitField(_, int_val) => int_val.size(target).ok_or_else(|| {
+                "128-bit ints and _BitInts aren't supported in structs with bitfields".to_owned()
+            })?,
+            Val::Float(FloatVal::c_float(_)) => 4,
+            Val::Float(FloatVal::c_double(_)) => 8,
+            Val::Bool(_) => 1,
+            Val::Ptr(_) => ptr_size,
+            _ => {
+                return Err("structs with bitfields can only contain ints, floats, bools, pointers, and bitfields".to_owned())
+            }
+        };
+        let align = align_of(size);
+        match field {
+            Val::BitField(0, _) => {
+                bit_offset = align_up(bit_offset, size * 8);
+            }
+            Val::BitField(width, _) => {
+                let width = *width as u64;
+                if width > size * 8 {
+                    return Err(format!(
+                        "a {width}-bit bitfield doesn't fit in its {size}-byte type"
+                    ));
+                }
+                if bit_offset % (size * 8) + width > size * 8 {
+                    bit_offset = align_up(bit_offset, size * 8);
+                }
+                layout.align = layout.align.max(align);
+            }
+            _ => {
+                bit_offset = align_up(bit_offset, 8 * align);
+                layout.align = layout.align.max(align);
+            }
+        }
+        layout.offsets.push(bit_offset);
+        layout.sizes.push(size);
+        if let Val::BitField(width, _) = field {
+            bit_offset += *width as u64;
+        } else {
+            bit_offset += size * 8;
+        }
+    }
+
+    let mut idx = 0;
+    for (is_bitfields, run) in struct_field_runs(fields) {
+        let mut named =
+            (idx..idx + run.len()).filter(|&i| !matches!(fields[i], Val::BitField(0, _)));
+        if is_bitfields {
+            if let (Some(first), Some(last)) = (named.clone().next(), named.next_back()) {
+                let Val::BitField(width, _) = &fields[last] else {
+                    unreachable!()
+                };
+                let end = layout.offsets[last] + *width as u64;
+                layout.runs.push((
+                    idx..idx + run.len(),
+                    layout.offsets[first] / 8..end.div_ceil(8),
+                ));
+            }
+        }
+        idx += run.len();
+    }
+    layout.end = bit_offset.div_ceil(8);
+    layout.size = align_up(layout.end, layout.align);
+    Ok(layout)
+}
+
+/// The target features needed to agree on how to pass these values
+/// (e.g. avx for __m256).
+pub fn target_features<'a>(vals: impl IntoIterator<Item = &'a Val>) -> Vec<&'static str> {
+    let mut features = vec![];
+    for val in vals {
+        val.visit(&mut |val| {
+            if let Val::Vector(vector_val) = val {
+                if let Some(feature) = vector_val.target_feature() {
+                    if !features.contains(&feature) {
+                        features.push(feature);
+                    }
+                }
+            }
+        });
+    }
+    features
+}
+
 impl Func {
+    /// The target features that the caller and callee need to enable to
+    /// agree on how to pass this function's values (e.g. avx for __m256).
+    pub fn target_features(&self) -> Vec<&'static str> {
+        target_features(self.all_inputs().chain(&self.output))
+    }
+
+    /// The inputs followed by the variadic inputs, which is the order
+    /// both sides report them in.
+    pub fn all_inputs(&self) -> impl Iterator<Item = &Val> {
+        self.inputs.iter().chain(&self.variadic_inputs)
+    }
+
+    /// Whether this function is variadic.
+    pub fn is_variadic(&self) -> bool {
+        !self.variadic_inputs.is_empty()
+    }
+
+    /// Whether every function pointer in this function's signature is
+    /// one of its args (the only place the backends know how to put them).
+    pub fn has_valid_fn_ptrs(&self) -> bool {
+        let mut nested = false;
+        for val in self.variadic_inputs.iter().chain(&self.output) {
+            val.visit(&mut |val| nested |= matches!(val, Val::FnPtr(..)));
+        }
+        for val in &self.inputs {
+            let vals: &[Val] = match val.without_rust_type() {
+                Val::FnPtr(_, _, args) => args,
+                _ => std::slice::from_ref(val),
+            };
+            for val in vals {
+                val.visit(&mut |val| nested |= matches!(val, Val::FnPtr(..)));
+            }
+        }
+        !nested
+    }
+
+    /// Whether every Ref with a non-default flavor in this function's
+    /// signature is directly one of its (non-variadic) inputs or its output.
+    pub fn has_valid_ref_flavors(&self) -> bool {
+        let mut nested = false;
+        let mut check = |val: &Val| {
+            nested |= matches!(val, Val::Ref(_, flavor) if !flavor.is_default());
+        };
+        for val in &self.variadic_inputs {
+            val.visit(&mut check);
+        }
+        for val in self.inputs.iter().chain(&self.output) {
+            match val {
+                Val::Ref(pointee, _) => pointee.visit(&mut check),
+                _ => val.visit(&mut check),
+            }
+        }
+        !nested
+    }
+
+    /// Whether every bitfield in this function's signature is directly
+    /// a field of a struct.
+    pub fn has_valid_bitfields(&self) -> bool {
+        let mut bitfields = 0;
+        let mut struct_bitfields = 0;
+        for val in self.all_inputs().chain(&self.output) {
+            val.visit(&mut |val| match val {
+                Val::BitField(..) => bitfields += 1,
+                Val::Struct(_, fields) | Val::ReprStruct(_, _, fields) => {
+                    struct_bitfields += fields
+                        .iter()
+                        .filter(|field| matches!(field, Val::BitField(..)))
+                        .count()
+                }
+                _ => {}
+            });
+        }
+        bitfields == struct_bitfields
+    }
+
+    /// Whether every flexible array member in this function's signature is
+    /// the last field of a struct that's passed by reference as an input.
+    pub fn has_valid_flex_arrays(&self) -> bool {
+        let mut flex_arrays = 0;
+        for val in self.all_inputs().chain(&self.output) {
+            val.visit(&mut |val| {
+                if let Val::FlexArray(..) = val {
+                    flex_arrays += 1;
+                }
+            });
+        }
+        let valid_flex_arrays = self
+            .inputs
+            .iter()
+            .filter(|val| match val {
+                Val::Ref(pointee, _) => match &**pointee {
+                    Val::Struct(_, fields) | Val::ReprStruct(_, _, fields) => {
+                        matches!(fields.last(), Some(Val::FlexArray(..)))
+                    }
+                    _ => false,
+                },
+                _ => false,
+            })
+            .count();
+        flex_arrays == valid_flex_arrays
+    }
+
     pub fn has_convention(&self, convention: CallingConvention) -> bool {
         self.conventions.iter().any(|&func_cc| {
-            (func_cc == CallingConvention::All && convention != CallingConvention::Handwritten)
+            (func_cc == CallingConvention::All
+                && convention != CallingConvention::Handwritten
+                && !convention.allows_unwinding())
                 || func_cc == convention
         })
     }
 }
 
+impl RawVal {
+    /// The value this describes, with every Named value in it replaced by
+    /// the value of its declared type, checking that arrays are homogeneous
+    /// along the way.
+    ///
+    /// `resolving` is the declared types that are being resolved, which
+    /// can't contain themselves.
+    fn resolve(
+        &self,
+        types: &HashMap<&str, &TypeDecl>,
+        resolving: &mut Vec<String>,
+    ) -> Result<Val, String> {
+        let out = match self {
+            RawVal::Named(name, data) => {
+                let data = resolve_all(data, types, resolving)?;
+                instantiate(name, data, None, types, resolving)?
+            }
+            RawVal::Array(vals) => {
+                let vals = resolve_all(vals, types, resolving)?;
+                let Some(elem_ty) = vals.first().cloned() else {
+                    return Err(format!(
+                        "an array needs elements to have a type (use an EmptyArray, or declare it in types)"
+                    ));
+                };
+                if vals.iter().any(|val| !val.same_type(&elem_ty)) {
+                    return Err(format!(
+                        "the elements of an array must all have the same type"
+                    ));
+                }
+                Val::Array(Box::new(elem_ty), vals)
+            }
+            RawVal::FlexArray(elem_ty, vals) => {
+                let elem_ty = elem_ty.resolve(types, resolving)?;
+                let vals = resolve_all(vals, types, resolving)?;
+                if vals.iter().any(|val| !val.same_type(&elem_ty)) {
+                    return Err(format!(
+                        "the elements of a flexible array member must all have its type"
+                    ));
+                }
+                Val::FlexArray(Box::new(elem_ty), vals)
+            }
+            RawVal::Ref(pointee, flavor) => {
+                Val::Ref(Box::new(pointee.resolve(types, resolving)?), *flavor)
+            }
+            RawVal::EmptyArray(elem_ty) => {
+                Val::EmptyArray(Box::new(elem_ty.resolve(types, resolving)?))
+            }
+            RawVal::RustType(ty, val) => {
+                Val::RustType(ty.clone(), Box::new(val.resolve(types, resolving)?))
+            }
+            RawVal::Transparent(name, val) => {
+                Val::Transparent(name.clone(), Box::new(val.resolve(types, resolving)?))
+            }
+            RawVal::Struct(name, fields) => {
+                Val::Struct(name.clone(), resolve_all(fields, types, resolving)?)
+            }
+            RawVal::ReprStruct(name, repr, fields) => {
+                Val::ReprStruct(name.clone(), *repr, resolve_all(fields, types, resolving)?)
+            }
+            RawVal::Union(name, active, fields) => {
+                let fields = resolve_all(fields, types, resolving)?;
+                match types.get(name.as_str()) {
+                    Some(TypeDecl::Union(..)) => {
+                        instantiate(name, fields, Some(*active), types, resolving)?
+                    }
+                    Some(_) => return Err(format!("type {name} isn't declared as a union")),
+                    None => Val::Union(name.clone(), *active, fields),
+                }
+            }
+            RawVal::FnPtr(name, convention, args) => Val::FnPtr(
+                name.clone(),
+                *convention,
+                resolve_all(args, types, resolving)?,
+            ),
+            RawVal::DataEnum(name, repr, variants, active) => {
+                let mut resolved = vec![];
+                for (variant, fields) in variants {
+                    resolved.push((variant.clone(), resolve_all(fields, types, resolving)?));
+                }
+                Val::DataEnum(name.clone(), *repr, resolved, *active)
+            }
+            RawVal::Enum(name, repr, variants, active) => {
+                Val::Enum(name.clone(), *repr, variants.clone(), *active)
+            }
+            RawVal::Int(int_val) => Val::Int(int_val.clone()),
+            RawVal::Float(float_val) => Val::Float(float_val.clone()),
+            RawVal::Complex(re, im) => Val::Complex(re.clone(), im.clone()),
+            RawVal::Bool(val) => Val::Bool(*val),
+            RawVal::Ptr(addr) => Val::Ptr(*addr),
+            RawVal::Vector(vector_val) => Val::Vector(vector_val.clone()),
+            RawVal::BitField(width, int_val) => Val::BitField(*width, int_val.clone()),
+            RawVal::CStr(string) => Val::CStr(string.clone()),
+            RawVal::WideCStr(string) => Val::WideCStr(string.clone()),
+            RawVal::Atomic(int_val) => Val::Atomic(int_val.clone()),
+        };
+        Ok(out)
+    }
+}
+
+/// The value of the declared type `name` with the given (resolved) data,
+/// and the member to make active if it's a union (see TypeDecl::instantiate).
+fn instantiate(
+    name: &str,
+    data: Vec<Val>,
+    active: Option<usize>,
+    types: &HashMap<&str, &TypeDecl>,
+    resolving: &mut Vec<String>,
+) -> Result<Val, String> {
+    let decl = types
+        .get(name)
+        .ok_or_else(|| format!("type {name} isn't declared"))?;
+    if resolving.iter().any(|resolving| resolving == name) {
+        return Err(format!("type {name} contains itself"));
+    }
+    resolving.push(name.to_owned());
+    let out = decl.instantiate(data, active, types, resolving);
+    resolving.pop();
+    out
+}
+
+/// [`RawVal::resolve`] every value in a list.
+fn resolve_all(
+    vals: &[RawVal],
+    types: &HashMap<&str, &TypeDecl>,
+    resolving: &mut Vec<String>,
+) -> Result<Vec<Val>, String> {
+    vals.iter()
+        .map(|val| val.resolve(types, resolving))
+        .collect()
+}
+
+impl TypeDecl {
+    pub fn name(&self) -> &str {
+        match self {
+            TypeDecl::Struct(name, _)
+            | TypeDecl::ReprStruct(name, _, _)
+            | TypeDecl::Union(name, _)
+            | TypeDecl::Array(name, _, _)
+            | TypeDecl::Alias(name, _) => name,
+        }
+    }
+
+    /// The value of this type with the given (resolved) data (see
+    /// RawVal::Named).
+    ///
+    /// For a union, `active` is the member that the data is the value of,
+    /// or else it's the first member with the same type.
+    fn instantiate(
+        &self,
+        data: Vec<Val>,
+        active: Option<usize>,
+        types: &HashMap<&str, &TypeDecl>,
+        resolving: &mut Vec<String>,
+    ) -> Result<Val, String> {
+        let name = self.name();
+        // Without data, the dummies are the value
+        let check_data = |dummies: Vec<Val>, data: Vec<Val>| {
+            if data.is_empty() {
+                return Ok(dummies);
+            }
+            if data.len() != dummies.len() {
+                return Err(format!(
+                    "{name} has {} values, but was given {}",
+                    dummies.len(),
+                    data.len()
+                ));
+            }
+            for (idx, (dummy, val)) in dummies.iter().zip(&data).enumerate() {
+                if !val.same_type(dummy) {
+                    return Err(format!("value {idx} of {name} has the wrong type"));
+                }
+            }
+            Ok(data)
+        };
+        let out = match self {
+            TypeDecl::Struct(_, fields) => {
+                let fields = check_data(resolve_all(fields, types, resolving)?, data)?;
+                Val::Struct(name.to_owned(), fields)
+            }
+            TypeDecl::ReprStruct(_, repr, fields) => {
+                let fields = check_data(resolve_all(fields, types, resolving)?, data)?;
+                Val::ReprStruct(name.to_owned(), *repr, fields)
+            }
+            TypeDecl::Union(_, members) => {
+                let mut members = resolve_all(members, types, resolving)?;
+                if active.is_some_and(|active| active >= members.len()) {
+                    return Err(format!("{name} only has {} members", members.len()));
+                }
+                let active = match (&data[..], active) {
+                    ([], active) => active.unwrap_or(0),
+                    ([val], Some(active)) => {
+                        if !members[active].same_type(val) {
+                            return Err(format!("member {active} of {name} has the wrong type"));
+                        }
+                        members[active] = val.clone();
+                        active
+                    }
+                    ([val], None) => {
+                        let active = members
+                            .iter()
+                            .position(|member| member.same_type(val))
+                            .ok_or_else(|| format!("{name} has no member with that type"))?;
+                        members[active] = val.clone();
+                        active
+                    }
+                    _ => return Err(format!("a value of union {name} is one member's value")),
+                };
+                Val::Union(name.to_owned(), active, members)
+            }
+            TypeDecl::Array(_, elem_ty, len) => {
+                let elem_ty = elem_ty.resolve(types, resolving)?;
+                let elems = check_data(vec![elem_ty.clone(); *len], data)?;
+                if elems.is_empty() {
+                    Val::EmptyArray(Box::new(elem_ty))
+                } else {
+                    Val::Array(Box::new(elem_ty), elems)
+                }
+            }
+            TypeDecl::Alias(_, ty) => {
+                let ty = ty.resolve(types, resolving)?;
+                check_data(vec![ty], data)?.remove(0)
+            }
+        };
+        Ok(out)
+    }
+}
+
+impl Test<RawVal> {
+    /// This test with every Named value in it replaced by the value of its
+    /// declared type, checking that its data has the right types (and
+    /// that every array is homogeneous).
+    pub fn resolve_types(self) -> Result<Test, String> {
+        let mut types = HashMap::new();
+        for decl in &self.types {
+            if types.insert(decl.name(), decl).is_some() {
+                return Err(format!("type {} is declared twice", decl.name()));
+            }
+        }
+        let mut funcs = vec![];
+        for func in &self.funcs {
+            let resolve = |vals: &[RawVal]| {
+                resolve_all(vals, &types, &mut vec![]).map_err(|e| format!("{}: {e}", func.name))
+            };
+            funcs.push(Func {
+                name: func.name.clone(),
+                conventions: func.conventions.clone(),
+                inputs: resolve(&func.inputs)?,
+                variadic_inputs: resolve(&func.variadic_inputs)?,
+                output: resolve(func.output.as_slice())?.pop(),
+                callee_unwinds: func.callee_unwinds,
+            });
+        }
+        Ok(Test {
+            name: self.name,
+            types: self.types,
+            funcs,
+            out_of_process: self.out_of_process,
+        })
+    }
+}
+
 impl Test {
     pub fn has_convention(&self, convention: CallingConvention) -> bool {
         self.funcs
             .iter()
             .any(|func| func.has_convention(convention))
     }
+
+    /// Every input and output of every function.
+    pub fn vals(&self) -> impl Iterator<Item = &Val> {
+        self.funcs
+            .iter()
+            .flat_map(|func| func.all_inputs().chain(&func.output))
+    }
+
+    /// Whether any value in this test (including nested ones) satisfies `pred`.
+    pub fn uses(&self, pred: impl Fn(&Val) -> bool) -> bool {
+        let mut found = false;
+        for val in self.vals() {
+            val.visit(&mut |val| found |= pred(val));
+        }
+        found
+    }
+
+    /// The union of the target features of every function using `convention`.
+    pub fn target_features(&self, convention: CallingConvention) -> Vec<&'static str> {
+        let mut features = vec![];
+        for func in &self.funcs {
+            if !func.has_convention(convention) {
+                continue;
+            }
+            for feature in func.target_features() {
+                if !features.contains(&feature) {
+                    features.push(feature);
+                }
+            }
+        }
+        features
+    }
 }
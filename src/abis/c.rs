@@ -5,7 +5,12 @@ pub static C_TEST_PREFIX: &str = include_str!("../../harness/c_test_prefix.h");
 
 pub struct CcAbiImpl {
     cc_flavor: CCFlavor,
+    /// The major version of the compiler, if it would tell us.
+    cc_version: Option<u32>,
+    /// The first line of the compiler's `--version`, if it would tell us.
+    cc_identity: Option<String>,
     platform: Platform,
+    target: Target,
     mode: &'static str,
 }
 
@@ -16,6 +21,44 @@ enum CCFlavor {
     Msvc,
 }
 
+/// The gcc and ar to use for the target (the cross toolchain, if it isn't the host).
+fn gnu_tools(target: &Target) -> (String, String) {
+    match target.gnu_prefix() {
+        Some(prefix) => (format!("{prefix}-gcc"), format!("{prefix}-ar")),
+        None => (String::from("gcc"), String::from("ar")),
+    }
+}
+
+/// Runs a C compiler (or archiver), failing if it does.
+fn run_c_tool(cmd: &mut Command) -> Result<(), BuildError> {
+    let out = cmd.output()?;
+    if !out.status.success() {
+        return Err(BuildError::CTool(out));
+    }
+    Ok(())
+}
+
+/// Makes a fresh static library out of the object file (appending to one
+/// left over from a previous run would link its stale objects).
+fn archive(ar: &str, obj_path: &Path, lib_path: &Path) -> Result<(), BuildError> {
+    match std::fs::remove_file(lib_path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        result => result?,
+    }
+    run_c_tool(Command::new(ar).arg("cq").arg(lib_path).arg(obj_path))?;
+    run_c_tool(Command::new(ar).arg("s").arg(lib_path))
+}
+
+/// The first line a gcc-like compiler prints for this flag, if it would tell us.
+fn compiler_output(mut compiler: Command, flag: &str) -> Option<String> {
+    let output = compiler.arg(flag).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8(output.stdout).ok()?;
+    Some(output.lines().next()?.trim().to_owned())
+}
+
 #[derive(PartialEq)]
 enum Platform {
     Windows,
@@ -43,6 +86,18 @@ impl AbiImpl for CcAbiImpl {
         test: &Test,
         convention: CallingConvention,
     ) -> Result<(), BuildError> {
+        // The only thing C could unwind with is a foreign exception, which
+        // Rust aborts on catching (and C callers can only catch through Rust).
+        if test
+            .funcs
+            .iter()
+            .any(|func| func.has_convention(convention) && func.callee_unwinds)
+        {
+            return Err(GenerateError::CUnsupported(format!(
+                "C callees can't unwind in a way the caller can catch"
+            ))
+            .into());
+        }
         self.write_c_prefix(f, test)?;
 
         // Generate the impls
@@ -55,12 +110,53 @@ impl AbiImpl for CcAbiImpl {
 
             writeln!(f)?;
             for (idx, input) in function.inputs.iter().enumerate() {
+                if let Val::FnPtr(_, _, args) = input.without_rust_type() {
+                    // The function reports these args for us
+                    self.write_c_fn_ptr_args(f, args, ARG_NAMES[idx])?;
+                    write!(f, "    {}(", ARG_NAMES[idx])?;
+                    for (arg_idx, arg) in args.iter().enumerate() {
+                        if arg_idx != 0 {
+                            write!(f, ", ")?;
+                        }
+                        let arg_name = format!("{}_{}", ARG_NAMES[idx], ARG_NAMES[arg_idx]);
+                        write!(f, "{}", self.c_arg_pass(arg, &arg_name)?)?;
+                    }
+                    writeln!(f, ");")?;
+                    continue;
+                }
                 writeln!(
                     f,
                     "{}",
                     self.c_write_val(input, "CALLEE_INPUTS", ARG_NAMES[idx], false)?
                 )?;
             }
+            if function.is_variadic() {
+                let last_arg = if function.output.as_ref().is_some_and(|output| {
+                    matches!(self.c_out_param(output, OUT_PARAM_NAME), Ok(Some(_)))
+                }) {
+                    OUT_PARAM_NAME
+                } else {
+                    ARG_NAMES[function.inputs.len() - 1]
+                };
+                writeln!(f, "    va_list varargs;")?;
+                writeln!(f, "    va_start(varargs, {last_arg});")?;
+                for (idx, input) in function.variadic_inputs.iter().enumerate() {
+                    let arg_name = ARG_NAMES[function.inputs.len() + idx];
+                    writeln!(
+                        f,
+                        "    {} = ({})va_arg(varargs, {});",
+                        self.c_arg_decl(input, arg_name)?,
+                        self.c_arg_type(input)?,
+                        self.c_va_arg_type(input)?
+                    )?;
+                    writeln!(
+                        f,
+                        "{}",
+                        self.c_write_val(input, "CALLEE_INPUTS", arg_name, false)?
+                    )?;
+                }
+                writeln!(f, "    va_end(varargs);")?;
+            }
             writeln!(f)?;
             if let Some(output) = &function.output {
                 writeln!(
@@ -105,7 +201,26 @@ impl AbiImpl for CcAbiImpl {
         }
 
         writeln!(f)?;
-        writeln!(f, "void do_test(void) {{")?;
+
+        // Functions that unwind have to be called through the harness' CATCH_UNWIND,
+        // so their calls get wrapped up in their own functions.
+        for function in &test.funcs {
+            if !function.has_convention(convention) || !function.callee_unwinds {
+                continue;
+            }
+            let target_attr = self.c_target_attr(&function.target_features());
+            writeln!(
+                f,
+                "static {target_attr}void call_{}(void* unused) {{",
+                function.name
+            )?;
+            self.write_c_call(f, function)?;
+            writeln!(f, "}}")?;
+            writeln!(f)?;
+        }
+
+        let target_attr = self.c_target_attr(&test.target_features(convention));
+        writeln!(f, "{target_attr}void do_test(void) {{")?;
 
         // Generate the impls
         for function in &test.funcs {
@@ -114,61 +229,21 @@ impl AbiImpl for CcAbiImpl {
             }
             // Add an extra scope to avoid clashes between subtests
             writeln!(f, "{{")?;
-            // Inputs
-            for (idx, input) in function.inputs.iter().enumerate() {
+            if function.callee_unwinds {
+                // Report whether we caught the unwind, to match the callee
+                // reporting that it started one.
                 writeln!(
                     f,
-                    "    {} = {};",
-                    self.c_var_decl(input, ARG_NAMES[idx])?,
-                    self.c_val(input)?
+                    "    bool caught = CATCH_UNWIND(call_{}, 0);",
+                    function.name
                 )?;
                 writeln!(
                     f,
                     "{}",
-                    self.c_write_val(input, "CALLER_INPUTS", ARG_NAMES[idx], true)?
+                    self.c_write_val(&Val::Bool(true), "CALLER_OUTPUTS", "caught", true)?
                 )?;
-            }
-            writeln!(f)?;
-
-            // Output
-            let pass_out = if let Some(output) = &function.output {
-                if let Some(out_param_var) = self.c_out_param_var(output, OUTPUT_NAME)? {
-                    writeln!(f, "    {};", out_param_var)?;
-                    write!(f, "    ")?;
-                    true
-                } else {
-                    write!(f, "    {} = ", self.c_var_decl(output, OUTPUT_NAME)?)?;
-                    false
-                }
             } else {
-                write!(f, "    ")?;
-                false
-            };
-
-            // Do the actual call
-            write!(f, "{}(", function.name)?;
-            for (idx, input) in function.inputs.iter().enumerate() {
-                if idx != 0 {
-                    write!(f, ", ")?;
-                }
-                write!(f, "{}", self.c_arg_pass(input, ARG_NAMES[idx])?)?;
-            }
-            if pass_out {
-                let pass = self.c_arg_pass(function.output.as_ref().unwrap(), OUTPUT_NAME)?;
-                if function.inputs.is_empty() {
-                    write!(f, "{}", pass)?;
-                } else {
-                    write!(f, ", {}", pass)?;
-                }
-            }
-            writeln!(f, ");")?;
-
-            if let Some(output) = &function.output {
-                writeln!(
-                    f,
-                    "{}",
-                    self.c_write_val(output, "CALLER_OUTPUTS", OUTPUT_NAME, true)?
-                )?;
+                self.write_c_call(f, function)?;
             }
             writeln!(f, "    FINISHED_FUNC(CALLER_INPUTS, CALLER_OUTPUTS);")?;
             writeln!(f, "}}")?;
@@ -178,41 +253,70 @@ impl AbiImpl for CcAbiImpl {
         Ok(())
     }
 
-    fn compile_callee(&self, src_path: &Path, lib_name: &str) -> Result<String, BuildError> {
+    fn compile_callee(
+        &self,
+        test: &Test,
+        src_path: &Path,
+        lib_name: &str,
+    ) -> Result<String, BuildError> {
+        // Only pay for unwind tables when something is going to unwind
+        // through our frames.
+        let exceptions = test.funcs.iter().any(|func| func.callee_unwinds);
         match self.mode {
-            "cc" => self.compile_cc(src_path, lib_name),
-            "gcc" => self.compile_gcc(src_path, lib_name),
-            "clang" => self.compile_clang(src_path, lib_name),
+            "cc" => self.compile_cc(src_path, lib_name, exceptions),
+            "gcc" => self.compile_gcc(src_path, lib_name, exceptions),
+            "clang" => self.compile_clang(src_path, lib_name, exceptions),
             "msvc" => self.compile_msvc(src_path, lib_name),
             _ => unimplemented!("unknown c compiler"),
         }
     }
 
-    fn compile_caller(&self, src_path: &Path, lib_name: &str) -> Result<String, BuildError> {
-        match self.mode {
-            "cc" => self.compile_cc(src_path, lib_name),
-            "gcc" => self.compile_gcc(src_path, lib_name),
-            "clang" => self.compile_clang(src_path, lib_name),
-            "msvc" => self.compile_msvc(src_path, lib_name),
-            _ => unimplemented!("unknown c compiler"),
-        }
+    fn compile_caller(
+        &self,
+        test: &Test,
+        src_path: &Path,
+        lib_name: &str,
+    ) -> Result<String, BuildError> {
+        // Currently no need to be different
+        self.compile_callee(test, src_path, lib_name)
     }
 }
 
 impl CcAbiImpl {
-    pub fn new(_system_info: &Config, mode: &'static str) -> Self {
-        let compiler = cc::Build::new().get_compiler();
-        let cc_flavor = if compiler.is_like_msvc() {
-            CCFlavor::Msvc
-        } else if compiler.is_like_gnu() {
-            CCFlavor::Gcc
-        } else if compiler.is_like_clang() {
-            CCFlavor::Clang
+    pub fn new(system_info: &Config, mode: &'static str) -> Self {
+        let target = system_info.target.clone();
+        // Ask the compiler that this mode actually runs, not just the default one
+        let (cc_flavor, compiler): (_, Box<dyn Fn() -> Command>) = match mode {
+            "gcc" => {
+                let gcc = gnu_tools(&target).0;
+                (CCFlavor::Gcc, Box::new(move || Command::new(&gcc)))
+            }
+            "clang" => (CCFlavor::Clang, Box::new(|| Command::new("clang"))),
+            _ => {
+                let compiler = cc::Build::new().get_compiler();
+                let cc_flavor = if compiler.is_like_msvc() {
+                    CCFlavor::Msvc
+                } else if compiler.is_like_gnu() {
+                    CCFlavor::Gcc
+                } else if compiler.is_like_clang() {
+                    CCFlavor::Clang
+                } else {
+                    panic!("Unknown compiler flavour for CC");
+                };
+                (cc_flavor, Box::new(move || compiler.to_command()))
+            }
+        };
+        let (cc_version, cc_identity) = if cc_flavor == CCFlavor::Msvc {
+            (None, None)
         } else {
-            panic!("Unknown compiler flavour for CC");
+            (
+                compiler_output(compiler(), "-dumpversion")
+                    .and_then(|version| version.split('.').next()?.parse().ok()),
+                compiler_output(compiler(), "--version"),
+            )
         };
 
-        let platform = if cfg!(target_os = "windows") {
+        let platform = if target.is_windows() {
             Platform::Windows
         } else {
             Platform::Unixy
@@ -220,13 +324,37 @@ impl CcAbiImpl {
 
         Self {
             cc_flavor,
+            cc_version,
+            cc_identity,
             platform,
+            target,
             mode,
         }
     }
 
-    fn compile_cc(&self, src_path: &Path, lib_name: &str) -> Result<String, BuildError> {
-        cc::Build::new()
+    /// The compiler this mode runs, for reports (e.g. `gcc (Debian 12.2.0-14) 12.2.0`).
+    pub fn compiler(&self) -> String {
+        match &self.cc_identity {
+            Some(identity) => identity.clone(),
+            None => match self.cc_flavor {
+                CCFlavor::Clang => String::from("clang (unknown version)"),
+                CCFlavor::Gcc => String::from("gcc (unknown version)"),
+                CCFlavor::Msvc => String::from("msvc"),
+            },
+        }
+    }
+
+    fn compile_cc(
+        &self,
+        src_path: &Path,
+        lib_name: &str,
+        exceptions: bool,
+    ) -> Result<String, BuildError> {
+        let mut build = cc::Build::new();
+        if exceptions {
+            build.flag_if_supported("-fexceptions");
+        }
+        build
             .file(src_path)
             .opt_level(0)
             .cargo_metadata(false)
@@ -235,51 +363,60 @@ impl CcAbiImpl {
         Ok(String::from(lib_name))
     }
 
-    fn compile_clang(&self, src_path: &Path, lib_name: &str) -> Result<String, BuildError> {
+    fn compile_clang(
+        &self,
+        src_path: &Path,
+        lib_name: &str,
+        exceptions: bool,
+    ) -> Result<String, BuildError> {
         let base_path = PathBuf::from("target/temp/");
         let obj_path = base_path.join(format!("{lib_name}.o"));
         let lib_path = base_path.join(format!("lib{lib_name}.a"));
-        Command::new("clang")
-            .arg("-ffunction-sections")
+        let mut cmd = Command::new("clang");
+        if !self.target.is_host() {
+            cmd.arg(format!("--target={}", self.target.triple));
+        }
+        if exceptions {
+            cmd.arg("-fexceptions");
+        }
+        cmd.arg("-ffunction-sections")
             .arg("-fdata-sections")
             .arg("-fPIC")
             .arg("-o")
             .arg(&obj_path)
             .arg("-c")
-            .arg(&src_path)
-            .status()
-            .unwrap();
-        Command::new("ar")
-            .arg("cq")
-            .arg(&lib_path)
-            .arg(&obj_path)
-            .status()
-            .unwrap();
-        Command::new("ar").arg("s").arg(&lib_path).status().unwrap();
+            .arg(&src_path);
+        run_c_tool(&mut cmd)?;
+        archive("ar", &obj_path, &lib_path)?;
         Ok(String::from(lib_name))
     }
 
-    fn compile_gcc(&self, src_path: &Path, lib_name: &str) -> Result<String, BuildError> {
+    fn compile_gcc(
+        &self,
+        src_path: &Path,
+        lib_name: &str,
+        exceptions: bool,
+    ) -> Result<String, BuildError> {
         let base_path = PathBuf::from("target/temp/");
         let obj_path = base_path.join(format!("{lib_name}.o"));
         let lib_path = base_path.join(format!("lib{lib_name}.a"));
-        Command::new("gcc")
-            .arg("-ffunction-sections")
+        let (gcc, ar) = gnu_tools(&self.target);
+        let mut cmd = Command::new(gcc);
+        if self.target.is_x86() {
+            cmd.arg("-m32");
+        }
+        if exceptions {
+            cmd.arg("-fexceptions");
+        }
+        cmd.arg("-ffunction-sections")
             .arg("-fdata-sections")
             .arg("-fPIC")
             .arg("-o")
             .arg(&obj_path)
             .arg("-c")
-            .arg(&src_path)
-            .status()
-            .unwrap();
-        Command::new("ar")
-            .arg("cq")
-            .arg(&lib_path)
-            .arg(&obj_path)
-            .status()
-            .unwrap();
-        Command::new("ar").arg("s").arg(&lib_path).status().unwrap();
+            .arg(&src_path);
+        run_c_tool(&mut cmd)?;
+        archive(&ar, &obj_path, &lib_path)?;
         Ok(String::from(lib_name))
     }
 
@@ -313,14 +450,45 @@ impl CcAbiImpl {
                 // All is sugar, we shouldn't get here!
                 return Err(GenerateError::UnsupportedConvention);
             }
-            System | Win64 | Sysv64 | Aapcs => {
+            SystemUnwind => {
+                // "system" is only something other than C on 32-bit Windows
+                if self.platform == Windows && self.target.is_x86() {
+                    return Err(GenerateError::UnsupportedConvention);
+                }
+                ""
+            }
+            System | Aapcs => {
                 // Don't want to think about these yet, I think they're
                 // all properly convered by other ABIs
                 return Err(GenerateError::UnsupportedConvention);
             }
-            C => "",
+            Win64 => {
+                // gcc and clang can use the Windows x64 convention on any
+                // x86_64 platform, MSVC only has it as the native convention.
+                if self.target.is_x86_64() {
+                    match self.cc_flavor {
+                        Msvc => "",
+                        Gcc | Clang => "__attribute__((ms_abi)) ",
+                    }
+                } else {
+                    return Err(GenerateError::UnsupportedConvention);
+                }
+            }
+            Sysv64 => {
+                if self.target.is_x86_64() {
+                    match self.cc_flavor {
+                        Msvc => return Err(GenerateError::UnsupportedConvention),
+                        Gcc | Clang => "__attribute__((sysv_abi)) ",
+                    }
+                } else {
+                    return Err(GenerateError::UnsupportedConvention);
+                }
+            }
+            // C has no notion of unwinding, so this is just C compiled
+            // with unwind tables (-fexceptions).
+            C | CUnwind => "",
             Cdecl => {
-                if self.platform == Windows {
+                if self.platform == Windows || self.target.is_x86() {
                     match self.cc_flavor {
                         Msvc => "__cdecl ",
                         Gcc | Clang => "__attribute__((cdecl)) ",
@@ -330,7 +498,7 @@ impl CcAbiImpl {
                 }
             }
             Stdcall => {
-                if self.platform == Windows {
+                if self.platform == Windows || self.target.is_x86() {
                     match self.cc_flavor {
                         Msvc => "__stdcall ",
                         Gcc | Clang => "__attribute__((stdcall)) ",
@@ -340,7 +508,7 @@ impl CcAbiImpl {
                 }
             }
             Fastcall => {
-                if self.platform == Windows {
+                if self.platform == Windows || self.target.is_x86() {
                     match self.cc_flavor {
                         Msvc => "__fastcall ",
                         Gcc | Clang => "__attribute__((fastcall)) ",
@@ -359,11 +527,126 @@ impl CcAbiImpl {
                     return Err(GenerateError::UnsupportedConvention);
                 }
             }
+            Thiscall => {
+                if self.target.is_x86() {
+                    match self.cc_flavor {
+                        Msvc => "__thiscall ",
+                        Gcc | Clang => "__attribute__((thiscall)) ",
+                    }
+                } else {
+                    return Err(GenerateError::UnsupportedConvention);
+                }
+            }
         };
 
         Ok(val)
     }
 
+    /// Emit the caller's side of a subtest: initialize and report the
+    /// inputs, do the call, and report the output.
+    fn write_c_call(&self, f: &mut dyn Write, function: &Func) -> Result<(), BuildError> {
+        // Inputs
+        for (idx, input) in function.inputs.iter().enumerate() {
+            writeln!(
+                f,
+                "    {} = {};",
+                self.c_var_decl(input, ARG_NAMES[idx])?,
+                self.c_val(input)?
+            )?;
+            if let Val::FnPtr(_, _, args) = input.without_rust_type() {
+                // We report the args that the callee should call it with
+                self.write_c_fn_ptr_args(f, args, ARG_NAMES[idx])?;
+            }
+            writeln!(
+                f,
+                "{}",
+                self.c_write_val(input, "CALLER_INPUTS", ARG_NAMES[idx], true)?
+            )?;
+        }
+        for (idx, input) in function.variadic_inputs.iter().enumerate() {
+            let arg_name = ARG_NAMES[function.inputs.len() + idx];
+            writeln!(
+                f,
+                "    {} = {};",
+                self.c_var_decl(input, arg_name)?,
+                self.c_val(input)?
+            )?;
+            writeln!(
+                f,
+                "{}",
+                self.c_write_val(input, "CALLER_INPUTS", arg_name, true)?
+            )?;
+        }
+        writeln!(f)?;
+
+        // Output
+        let pass_out = if let Some(output) = &function.output {
+            if let Some(out_param_var) = self.c_out_param_var(output, OUTPUT_NAME)? {
+                writeln!(f, "    {};", out_param_var)?;
+                write!(f, "    ")?;
+                true
+            } else {
+                write!(f, "    {} = ", self.c_var_decl(output, OUTPUT_NAME)?)?;
+                false
+            }
+        } else {
+            write!(f, "    ")?;
+            false
+        };
+
+        // Do the actual call
+        write!(f, "{}(", function.name)?;
+        for (idx, input) in function.inputs.iter().enumerate() {
+            if idx != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", self.c_arg_pass(input, ARG_NAMES[idx])?)?;
+        }
+        if pass_out {
+            let pass = self.c_arg_pass(function.output.as_ref().unwrap(), OUTPUT_NAME)?;
+            if function.inputs.is_empty() {
+                write!(f, "{}", pass)?;
+            } else {
+                write!(f, ", {}", pass)?;
+            }
+        }
+        // The compiler takes care of promoting these
+        for (idx, input) in function.variadic_inputs.iter().enumerate() {
+            let arg_name = ARG_NAMES[function.inputs.len() + idx];
+            write!(f, ", {}", self.c_arg_pass(input, arg_name)?)?;
+        }
+        writeln!(f, ");")?;
+
+        if let Some(output) = &function.output {
+            writeln!(
+                f,
+                "{}",
+                self.c_write_val(output, "CALLER_OUTPUTS", OUTPUT_NAME, true)?
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Declare and initialize the args for a function pointer arg named
+    /// `arg_name` to be called with.
+    fn write_c_fn_ptr_args(
+        &self,
+        f: &mut dyn Write,
+        args: &[Val],
+        arg_name: &str,
+    ) -> Result<(), BuildError> {
+        for (idx, arg) in args.iter().enumerate() {
+            let var_name = format!("{arg_name}_{}", ARG_NAMES[idx]);
+            writeln!(
+                f,
+                "    {} = {};",
+                self.c_var_decl(arg, &var_name)?,
+                self.c_val(arg)?
+            )?;
+        }
+        Ok(())
+    }
+
     // Emit a function signature
     fn write_c_signature(
         &self,
@@ -372,6 +655,18 @@ impl CcAbiImpl {
         convention: CallingConvention,
     ) -> Result<(), BuildError> {
         let convention_decl = self.c_convention_decl(convention)?;
+        if function.is_variadic() {
+            if !convention.allows_variadics() {
+                return Err(GenerateError::UnsupportedConvention.into());
+            }
+            if function.inputs.is_empty() {
+                return Err(GenerateError::CUnsupported(format!(
+                    "variadic functions need at least one fixed input"
+                ))
+                .into());
+            }
+        }
+        write!(f, "{}", self.c_target_attr(&function.target_features()))?;
 
         // First figure out the return (by-ref requires an out-param)
         let out_param = if let Some(output) = &function.output {
@@ -407,21 +702,64 @@ impl CcAbiImpl {
         } else if function.inputs.is_empty() {
             write!(f, "void")?;
         }
+        if function.is_variadic() {
+            write!(f, ", ...")?;
+        }
         write!(f, ")")?;
 
         Ok(())
     }
 
+    /// The attribute to enable the given target features on a function.
+    fn c_target_attr(&self, features: &[&str]) -> String {
+        if features.is_empty() || self.cc_flavor == CCFlavor::Msvc {
+            // MSVC doesn't need (or have) a way to do this per-function.
+            String::new()
+        } else {
+            format!("__attribute__((target(\"{}\"))) ", features.join(","))
+        }
+    }
+
     /// Every test should start by loading in the harness' "header"
     /// and forward-declaring any structs that will be used.
     fn write_c_prefix(&self, f: &mut dyn Write, test: &Test) -> Result<(), BuildError> {
         // Load test harness "headers"
         write!(f, "{}", C_TEST_PREFIX)?;
 
+        // Only pull in the vector types if we need them, this header
+        // doesn't exist on non-x86 platforms.
+        if test.uses(|val| matches!(val, Val::Vector(_))) {
+            writeln!(f, "#include <immintrin.h>")?;
+        }
+        if test.uses(|val| matches!(val, Val::Complex(..))) {
+            writeln!(f, "#include <complex.h>")?;
+        }
+        if test.uses(|val| matches!(val, Val::WideCStr(_))) {
+            writeln!(f, "#include <wchar.h>")?;
+        }
+
         // Forward-decl struct types
         let mut forward_decls = std::collections::HashMap::<String, String>::new();
         for function in &test.funcs {
-            for val in function.inputs.iter().chain(function.output.as_ref()) {
+            if !function.has_valid_fn_ptrs() {
+                return Err(GenerateError::CUnsupported(format!(
+                    "function pointers can only be used directly as args"
+                ))
+                .into());
+            }
+            if !function.has_valid_bitfields() {
+                return Err(GenerateError::CUnsupported(format!(
+                    "bitfields can only be fields of structs"
+                ))
+                .into());
+            }
+            if !function.has_valid_flex_arrays() {
+                return Err(GenerateError::CUnsupported(format!(
+                    "flexible array members can only be the last field of a struct passed by Ref as an input"
+                ))
+                .into());
+            }
+            for val in function.all_inputs().chain(&function.output) {
                 for (name, decl) in self.c_forward_decl(val)? {
                     match forward_decls.entry(name) {
                         std::collections::hash_map::Entry::Occupied(entry) => {
@@ -448,24 +786,211 @@ impl CcAbiImpl {
     fn c_forward_decl(&self, val: &Val) -> Result<Vec<(String, String)>, GenerateError> {
         use Val::*;
         match val {
-            Struct(name, fields) => {
+            Struct(name, fields) | ReprStruct(name, _, fields) => {
                 let mut results = vec![];
                 for field in fields.iter() {
                     results.extend(self.c_forward_decl(field)?);
                 }
                 let mut output = String::new();
                 let ref_name = format!("struct {name}");
-                output.push_str(&format!("struct {name} {{\n"));
+                let mut attrs = String::new();
+                let repr = if let ReprStruct(_, repr, _) = val {
+                    if fields.iter().any(|field| matches!(field, BitField(..))) {
+                        return Err(GenerateError::CUnsupported(format!(
+                            "bitfields in packed or aligned structs aren't supported"
+                        )));
+                    }
+                    Some(*repr)
+                } else {
+                    None
+                };
+                match repr {
+                    Some(StructRepr::Packed(n)) => {
+                        output.push_str(&format!("#pragma pack(push, {n})\n"));
+                    }
+                    Some(StructRepr::Aligned(n)) => {
+                        attrs = if self.cc_flavor == CCFlavor::Msvc {
+                            format!("__declspec(align({n})) ")
+                        } else {
+                            format!("__attribute__((aligned({n}))) ")
+                        };
+                    }
+                    None => {}
+                }
+                if fields.is_empty() && self.cc_flavor == CCFlavor::Msvc {
+                    return Err(GenerateError::CUnsupported(format!(
+                        "MSVC doesn't support empty structs"
+                    )));
+                }
+                output.push_str(&format!("struct {attrs}{name} {{\n"));
                 for (idx, field) in fields.iter().enumerate() {
                     let line = format!("    {};\n", self.c_field_decl(field, FIELD_NAMES[idx])?);
                     output.push_str(&line);
                 }
                 output.push_str("};\n");
+                if let Some(StructRepr::Packed(_)) = repr {
+                    output.push_str("#pragma pack(pop)\n");
+                }
+                results.push((ref_name, output));
+                Ok(results)
+            }
+            Union(name, _, fields) => {
+                let mut results = vec![];
+                for field in fields.iter() {
+                    results.extend(self.c_forward_decl(field)?);
+                }
+                let mut output = String::new();
+                let ref_name = format!("union {name}");
+                output.push_str(&format!("union {name} {{\n"));
+                for (idx, field) in fields.iter().enumerate() {
+                    let line = format!("    {};\n", self.c_field_decl(field, FIELD_NAMES[idx])?);
+                    output.push_str(&line);
+                }
+                output.push_str("};\n");
+                results.push((ref_name, output));
+                Ok(results)
+            }
+            Enum(name, repr, variants, _) => {
+                if variants.is_empty() {
+                    return Err(GenerateError::CUnsupported(format!(
+                        "enum {name} has no variants"
+                    )));
+                }
+                let mut output = String::new();
+                let ref_name = format!("enum {name}");
+                if let Some(int_ty) = self.c_enum_repr_type(*repr) {
+                    // Without a fixed underlying type the compiler picks the
+                    // size, and then there's nothing to test.
+                    if !self.c_supports_fixed_enum_types() {
+                        return Err(GenerateError::CUnsupported(format!(
+                            "enum {name} needs a fixed underlying type, which this C compiler doesn't support"
+                        )));
+                    }
+                    output.push_str(&format!("enum {name} : {int_ty} {{\n"));
+                } else {
+                    output.push_str(&format!("enum {name} {{\n"));
+                }
+                for (variant, discriminant) in variants {
+                    output.push_str(&format!("    {name}_{variant} = {discriminant},\n"));
+                }
+                output.push_str("};\n");
+                Ok(vec![(ref_name, output)])
+            }
+            DataEnum(name, repr, variants, _) => {
+                if variants.is_empty() {
+                    return Err(GenerateError::CUnsupported(format!(
+                        "enum {name} has no variants"
+                    )));
+                }
+                let mut results = vec![];
+                for (_, fields) in variants {
+                    for field in fields {
+                        results.extend(self.c_forward_decl(field)?);
+                    }
+                }
+                // The tagged union that RFC 2195 says the enum is laid out as
+                let mut output = String::new();
+                let tag_ty = if let Some(int_ty) = self.c_enum_repr_type(*repr) {
+                    format!("{int_ty}")
+                } else {
+                    output.push_str(&format!("enum {name}_Tag {{\n"));
+                    for (variant, _) in variants {
+                        output.push_str(&format!("    {name}_{variant},\n"));
+                    }
+                    output.push_str("};\n");
+                    format!("enum {name}_Tag")
+                };
+                for (variant, fields) in variants {
+                    // Fieldless variants don't need to be in the repr(C) union
+                    if *repr == EnumRepr::C && fields.is_empty() {
+                        continue;
+                    }
+                    output.push_str(&format!("struct {name}_{variant} {{\n"));
+                    if *repr != EnumRepr::C {
+                        output.push_str(&format!("    {tag_ty} tag;\n"));
+                    }
+                    for (idx, field) in fields.iter().enumerate() {
+                        let line =
+                            format!("    {};\n", self.c_field_decl(field, FIELD_NAMES[idx])?);
+                        output.push_str(&line);
+                    }
+                    output.push_str("};\n");
+                }
+                let ref_name = if *repr == EnumRepr::C {
+                    let has_payload = variants.iter().any(|(_, fields)| !fields.is_empty());
+                    if has_payload {
+                        output.push_str(&format!("union {name}_Payload {{\n"));
+                        for (variant, fields) in variants {
+                            if !fields.is_empty() {
+                                output
+                                    .push_str(&format!("    struct {name}_{variant} {variant};\n"));
+                            }
+                        }
+                        output.push_str("};\n");
+                    }
+                    output.push_str(&format!("struct {name} {{\n"));
+                    output.push_str(&format!("    {tag_ty} tag;\n"));
+                    if has_payload {
+                        output.push_str(&format!("    union {name}_Payload payload;\n"));
+                    }
+                    output.push_str("};\n");
+                    format!("struct {name}")
+                } else {
+                    output.push_str(&format!("union {name} {{\n"));
+                    for (variant, _) in variants {
+                        output.push_str(&format!("    struct {name}_{variant} {variant};\n"));
+                    }
+                    output.push_str("};\n");
+                    format!("union {name}")
+                };
                 results.push((ref_name, output));
                 Ok(results)
             }
-            Array(vals) => self.c_forward_decl(&vals[0]),
-            Ref(pointee) => self.c_forward_decl(pointee),
+            FnPtr(name, convention, args) => {
+                let mut results = vec![];
+                for arg in args.iter() {
+                    results.extend(self.c_forward_decl(arg)?);
+                }
+                let convention_decl = self
+                    .c_convention_decl(*convention)
+                    .map_err(|_| GenerateError::UnsupportedConvention)?;
+                let mut params = String::new();
+                for (idx, arg) in args.iter().enumerate() {
+                    if idx != 0 {
+                        params.push_str(", ");
+                    }
+                    params.push_str(&self.c_arg_decl(arg, ARG_NAMES[idx])?);
+                }
+                if args.is_empty() {
+                    params.push_str("void");
+                }
+                // The typedef, and the function the caller passes in for it,
+                // which reports the args it gets called with.
+                let mut output = String::new();
+                output.push_str(&format!(
+                    "typedef void ({convention_decl}*{name})({params});\n"
+                ));
+                output.push_str(&format!(
+                    "static {}void {convention_decl}{name}_impl({params}) {{\n",
+                    self.c_target_attr(&target_features(args))
+                ));
+                for (idx, arg) in args.iter().enumerate() {
+                    for path in self.c_var_paths(arg, ARG_NAMES[idx], false)? {
+                        output.push_str(&format!(
+                            "    WRITE(CALLEE_INPUTS, (char*)&{path}, (uint32_t)sizeof({path}));\n"
+                        ));
+                    }
+                }
+                output.push_str("    FINISHED_VAL(CALLEE_INPUTS);\n");
+                output.push_str("}\n");
+                results.push((name.clone(), output));
+                Ok(results)
+            }
+            Array(elem_ty, _) => self.c_forward_decl(elem_ty),
+            EmptyArray(elem_ty) | FlexArray(elem_ty, _) => self.c_forward_decl(elem_ty),
+            Ref(pointee, _) | RustType(_, pointee) | Transparent(_, pointee) => {
+                self.c_forward_decl(pointee)
+            }
             _ => Ok(vec![]),
         }
     }
@@ -474,16 +999,23 @@ impl CcAbiImpl {
     fn c_var_decl(&self, val: &Val, var_name: &str) -> Result<String, GenerateError> {
         use Val::*;
         let val = match val {
-            Ref(pointee) => self.c_var_decl(pointee, var_name)?,
-            Array(_) => {
+            Ref(pointee, _) => self.c_var_decl(pointee, var_name)?,
+            Array(..) => {
                 let mut cur_val = val;
                 let mut array_levels = String::new();
-                while let Val::Array(vals) = cur_val {
+                while let Val::Array(elem_ty, vals) = cur_val {
                     array_levels.push_str(&format!("[{}]", vals.len()));
-                    cur_val = &vals[0];
+                    cur_val = elem_ty;
                 }
                 format!("{} {var_name}{array_levels}", self.c_arg_type(cur_val)?)
             }
+            Struct(_, fields) | ReprStruct(_, _, fields)
+                if matches!(fields.last(), Some(FlexArray(..))) =>
+            {
+                // Only objects with static storage can have their flexible
+                // array member initialized (a GNU extension)
+                format!("static {} {var_name}", self.c_arg_type(val)?)
+            }
             normal_val => format!("{} {var_name}", self.c_arg_type(normal_val)?),
         };
         Ok(val)
@@ -491,12 +1023,12 @@ impl CcAbiImpl {
 
     /// The decl to use for a function arg (apply referenceness)
     fn c_arg_decl(&self, val: &Val, arg_name: &str) -> Result<String, GenerateError> {
-        let out = if let Val::Ref(pointee) = val {
+        let out = if let Val::Ref(pointee, _) = val {
             let mut cur_val = &**pointee;
             let mut array_levels = String::new();
-            while let Val::Array(vals) = cur_val {
+            while let Val::Array(elem_ty, vals) = cur_val {
                 array_levels.push_str(&format!("[{}]", vals.len()));
-                cur_val = &vals[0];
+                cur_val = elem_ty;
             }
             if array_levels.is_empty() {
                 format!("{}* {arg_name}", self.c_arg_type(cur_val)?)
@@ -515,12 +1047,12 @@ impl CcAbiImpl {
         val: &Val,
         out_param_name: &str,
     ) -> Result<Option<String>, GenerateError> {
-        let out = if let Val::Ref(pointee) = val {
+        let out = if let Val::Ref(pointee, _) = val {
             let mut cur_val = &**pointee;
             let mut array_levels = String::new();
-            while let Val::Array(vals) = cur_val {
+            while let Val::Array(elem_ty, vals) = cur_val {
                 array_levels.push_str(&format!("[{}]", vals.len()));
-                cur_val = &vals[0];
+                cur_val = elem_ty;
             }
             if array_levels.is_empty() {
                 Some(format!("{}* {out_param_name}", self.c_arg_type(cur_val)?))
@@ -542,7 +1074,7 @@ impl CcAbiImpl {
         val: &Val,
         output_name: &str,
     ) -> Result<Option<String>, GenerateError> {
-        if let Val::Ref(pointee) = val {
+        if let Val::Ref(pointee, _) = val {
             Ok(Some(self.c_var_decl(pointee, output_name)?))
         } else {
             Ok(None)
@@ -551,8 +1083,8 @@ impl CcAbiImpl {
 
     /// How to pass an argument
     fn c_arg_pass(&self, val: &Val, arg_name: &str) -> Result<String, GenerateError> {
-        if let Val::Ref(pointee) = val {
-            if let Val::Array(_) = &**pointee {
+        if let Val::Ref(pointee, _) = val {
+            if let Val::Array(..) = &**pointee {
                 Ok(format!("{arg_name}"))
             } else {
                 Ok(format!("&{arg_name}"))
@@ -569,7 +1101,7 @@ impl CcAbiImpl {
         var_name: &str,
         out_param_name: &str,
     ) -> Result<String, GenerateError> {
-        if let Val::Ref(_) = val {
+        if let Val::Ref(..) = val {
             Ok(format!(
                 "memcpy({out_param_name}, &{var_name}, sizeof({var_name}));"
             ))
@@ -583,16 +1115,28 @@ impl CcAbiImpl {
         use IntVal::*;
         use Val::*;
         let val = match val {
-            Ref(pointee) => {
+            Ref(pointee, _) => {
                 let mut cur_val = &**pointee;
-                while let Val::Array(vals) = cur_val {
-                    cur_val = &vals[0];
+                while let Val::Array(elem_ty, _) = cur_val {
+                    cur_val = elem_ty;
                 }
                 format!("{}*", self.c_arg_type(cur_val)?)
             }
+            // C doesn't care how Rust spells it
+            RustType(_, val) | Transparent(_, val) => self.c_arg_type(val)?,
             Ptr(_) => format!("void*"),
+            CStr(_) => format!("const char*"),
+            WideCStr(_) => format!("const wchar_t*"),
+            Atomic(int_val) => {
+                if self.cc_flavor == CCFlavor::Msvc {
+                    return Err(GenerateError::CUnsupported(format!(
+                        "MSVC doesn't support _Atomic"
+                    )));
+                }
+                format!("_Atomic({})", self.c_arg_type(&Int(int_val.clone()))?)
+            }
             Bool(_) => format!("bool"),
-            Array(_vals) => {
+            Array(..) => {
                 // C arrays are kinda fake due to how they decay in function arg
                 // position, so a ton of code needs to very delicately detect arrays
                 // and desugar them properly. Since most things eventually sink into
@@ -606,9 +1150,71 @@ impl CcAbiImpl {
                     "C Arrays can't be passed directly, wrap this in Ref"
                 )));
             }
-            Struct(name, _) => format!("struct {name}"),
+            Struct(name, _) | ReprStruct(name, _, _) => format!("struct {name}"),
+            Union(name, _, _) => format!("union {name}"),
+            Enum(name, _, _, _) => format!("enum {name}"),
+            DataEnum(name, EnumRepr::C, _, _) => format!("struct {name}"),
+            DataEnum(name, _, _, _) => format!("union {name}"),
+            FnPtr(name, _, _) => format!("{name}"),
+            BitField(..) => {
+                return Err(GenerateError::CUnsupported(format!(
+                    "bitfields can only be fields of structs"
+                )));
+            }
+            EmptyArray(_) | FlexArray(..) => {
+                return Err(GenerateError::CUnsupported(format!(
+                    "empty arrays and flexible array members can only be fields of structs"
+                )));
+            }
+            Vector(vector_val) => {
+                if !self.target.is_x86_64() {
+                    return Err(GenerateError::CUnsupported(format!(
+                        "SIMD vectors are only implemented for x86_64"
+                    )));
+                }
+                match vector_val {
+                    VectorVal::c__m128(_) => format!("__m128"),
+                    VectorVal::c__m128i(_) => format!("__m128i"),
+                    VectorVal::c__m256(_) => format!("__m256"),
+                    VectorVal::c__m256d(_) => format!("__m256d"),
+                }
+            }
             Float(FloatVal::c_double(_)) => format!("double"),
             Float(FloatVal::c_float(_)) => format!("float"),
+            Float(FloatVal::c_long_double(_)) => format!("long double"),
+            Float(FloatVal::c__float128(_)) => {
+                if !self.target.is_x86() && !self.target.is_x86_64() {
+                    return Err(GenerateError::CUnsupported(format!(
+                        "__float128 is only available on x86"
+                    )));
+                }
+                format!("__float128")
+            }
+            Float(FloatVal::c_Float16(_)) => format!("_Float16"),
+            Float(FloatVal::c__bf16(_)) => {
+                if !self.c_supports_bf16() {
+                    return Err(GenerateError::CUnsupported(format!(
+                        "__bf16 needs clang, or gcc 13+"
+                    )));
+                }
+                format!("__bf16")
+            }
+            Complex(re, im) => {
+                if self.cc_flavor == CCFlavor::Msvc {
+                    return Err(GenerateError::CUnsupported(format!(
+                        "MSVC doesn't support _Complex"
+                    )));
+                }
+                match (re, im) {
+                    (FloatVal::c_double(_), FloatVal::c_double(_)) => format!("double _Complex"),
+                    (FloatVal::c_float(_), FloatVal::c_float(_)) => format!("float _Complex"),
+                    _ => {
+                        return Err(GenerateError::CUnsupported(format!(
+                            "complex numbers must be made of two floats or two doubles"
+                        )))
+                    }
+                }
+            }
             Int(int_val) => match int_val {
                 c__int128(_) => format!("__int128_t"),
                 c_int64_t(_) => format!("int64_t"),
@@ -620,22 +1226,158 @@ impl CcAbiImpl {
                 c_uint32_t(_) => format!("uint32_t"),
                 c_uint16_t(_) => format!("uint16_t"),
                 c_uint8_t(_) => format!("uint8_t"),
+                c_char(_) => format!("char"),
+                c_schar(_) => format!("signed char"),
+                c_uchar(_) => format!("unsigned char"),
+                c_int(_) => format!("int"),
+                c_uint(_) => format!("unsigned int"),
+                c_long(_) => format!("long"),
+                c_ulong(_) => format!("unsigned long"),
+                c_size_t(_) => format!("size_t"),
+                c_ptrdiff_t(_) => format!("ptrdiff_t"),
+                c_intptr_t(_) => format!("intptr_t"),
+                c_uintptr_t(_) => format!("uintptr_t"),
+                c_BitInt { bits, signed, .. } => {
+                    if !self.c_supports_bit_int() {
+                        return Err(GenerateError::CUnsupported(format!(
+                            "_BitInt needs clang, or gcc 14+ on x86_64"
+                        )));
+                    }
+                    if *signed {
+                        format!("_BitInt({bits})")
+                    } else {
+                        format!("unsigned _BitInt({bits})")
+                    }
+                }
             },
         };
         Ok(val)
     }
 
+    /// The type to va_arg this value as, after the default argument promotions.
+    fn c_va_arg_type(&self, val: &Val) -> Result<String, GenerateError> {
+        use IntVal::*;
+        use Val::*;
+        let out = match val {
+            Bool(_) => format!("int"),
+            Float(FloatVal::c_float(_)) => format!("double"),
+            Int(c_int8_t(_) | c_int16_t(_) | c_uint8_t(_) | c_uint16_t(_)) => format!("int"),
+            Int(c_char(_) | c_schar(_) | c_uchar(_)) => format!("int"),
+            Int(c__int128(_) | c__uint128(_)) => {
+                return Err(GenerateError::CUnsupported(format!(
+                    "128-bit ints can't be passed as variadic args"
+                )))
+            }
+            Int(c_BitInt { .. }) => {
+                return Err(GenerateError::CUnsupported(format!(
+                    "_BitInts can't be passed as variadic args"
+                )))
+            }
+            Ref(pointee, _) if matches!(**pointee, Array(..)) => {
+                return Err(GenerateError::CUnsupported(format!(
+                    "arrays can't be passed as variadic args"
+                )))
+            }
+            Int(_) | Float(FloatVal::c_double(_)) | Ptr(_) | Ref(..) | CStr(_) | WideCStr(_) => {
+                self.c_arg_type(val)?
+            }
+            RustType(_, val) | Transparent(_, val) => self.c_va_arg_type(val)?,
+            Float(_) => {
+                return Err(GenerateError::CUnsupported(format!(
+                    "extended floats can't be passed as variadic args"
+                )))
+            }
+            Array(..) | Struct(..) | ReprStruct(..) | Union(..) | Enum(..) | DataEnum(..)
+            | Vector(_) | Complex(..) | FnPtr(..) | BitField(..) | EmptyArray(_)
+            | FlexArray(..) | Atomic(_) => {
+                return Err(GenerateError::CUnsupported(format!(
+                    "only scalars and Refs can be passed as variadic args"
+                )))
+            }
+        };
+        Ok(out)
+    }
+
+    /// Whether `enum X : T` works. It's C23, but clang has long supported it
+    /// as an extension, and gcc does since 13.
+    /// Whether the compiler has `__bf16` (gcc only got it in 13).
+    fn c_supports_bf16(&self) -> bool {
+        match self.cc_flavor {
+            CCFlavor::Clang => true,
+            CCFlavor::Gcc => self.cc_version.is_some_and(|version| version >= 13),
+            CCFlavor::Msvc => false,
+        }
+    }
+
+    fn c_supports_fixed_enum_types(&self) -> bool {
+        match self.cc_flavor {
+            CCFlavor::Clang => true,
+            CCFlavor::Gcc => self.cc_version.is_some_and(|version| version >= 13),
+            CCFlavor::Msvc => false,
+        }
+    }
+
+    /// Whether `_BitInt(N)` works. It's C23, but clang supports it as an
+    /// extension, and so does gcc since 14 (only on x86_64 at first).
+    fn c_supports_bit_int(&self) -> bool {
+        match self.cc_flavor {
+            CCFlavor::Clang => true,
+            CCFlavor::Gcc => {
+                self.target.is_x86_64() && self.cc_version.is_some_and(|version| version >= 14)
+            }
+            CCFlavor::Msvc => false,
+        }
+    }
+
+    /// The fixed underlying type to give an enum with this repr, if any.
+    fn c_enum_repr_type(&self, repr: EnumRepr) -> Option<&'static str> {
+        match repr {
+            EnumRepr::C => None,
+            EnumRepr::u8 => Some("uint8_t"),
+            EnumRepr::u16 => Some("uint16_t"),
+            EnumRepr::u32 => Some("uint32_t"),
+            EnumRepr::u64 => Some("uint64_t"),
+            EnumRepr::i8 => Some("int8_t"),
+            EnumRepr::i16 => Some("int16_t"),
+            EnumRepr::i32 => Some("int32_t"),
+            EnumRepr::i64 => Some("int64_t"),
+        }
+    }
+
     /// The type name to use for this value when it is stored in composite.
     ///
     /// This is separated out in case there's a type that needs different
     /// handling in this context to conform to a layout (i.e. how C arrays
     /// decay into pointers when used in function args).
     fn c_field_decl(&self, val: &Val, field_name: &str) -> Result<String, GenerateError> {
+        if let Val::BitField(width, int_val) = val {
+            let ty = self.c_arg_type(&Val::Int(int_val.clone()))?;
+            // Zero-width bitfields have to be unnamed
+            if *width == 0 {
+                return Ok(format!("{ty} : 0"));
+            }
+            return Ok(format!("{ty} {field_name} : {width}"));
+        }
+        // These are declarators like arrays are, so they nest the same way
+        match val {
+            Val::EmptyArray(elem_ty) => {
+                if self.cc_flavor == CCFlavor::Msvc {
+                    return Err(GenerateError::CUnsupported(format!(
+                        "MSVC doesn't support zero-length arrays"
+                    )));
+                }
+                return self.c_field_decl(elem_ty, &format!("{field_name}[0]"));
+            }
+            Val::FlexArray(elem_ty, _) => {
+                return self.c_field_decl(elem_ty, &format!("{field_name}[]"));
+            }
+            _ => {}
+        }
         let mut cur_val = val;
         let mut array_levels = String::new();
-        while let Val::Array(vals) = cur_val {
+        while let Val::Array(elem_ty, vals) = cur_val {
             array_levels.push_str(&format!("[{}]", vals.len()));
-            cur_val = &vals[0];
+            cur_val = elem_ty;
         }
         Ok(format!(
             "{} {field_name}{array_levels}",
@@ -648,10 +1390,19 @@ impl CcAbiImpl {
         use IntVal::*;
         use Val::*;
         let val = match val {
-            Ref(pointee) => self.c_val(pointee)?,
-            Ptr(addr) => format!("(void*){addr:#X}ull"),
+            Ref(pointee, _) | RustType(_, pointee) | Transparent(_, pointee) => {
+                self.c_val(pointee)?
+            }
+            Ptr(addr) => {
+                let addr = self.target.truncate_ptr(*addr);
+                format!("(void*)(uintptr_t){addr:#X}ull")
+            }
+            Atomic(int_val) => self.c_val(&Int(int_val.clone()))?,
+            CStr(string) => c_string_literal(string, false)?,
+            WideCStr(string) => c_string_literal(string, true)?,
             Bool(val) => format!("{val}"),
-            Array(vals) => {
+            EmptyArray(_) => format!("{{ }}"),
+            Array(_, vals) | FlexArray(_, vals) => {
                 let mut output = String::new();
                 output.push_str("{ ");
                 for (idx, elem) in vals.iter().enumerate() {
@@ -664,11 +1415,15 @@ impl CcAbiImpl {
                 output.push_str(" }");
                 output
             }
-            Struct(_name, fields) => {
+            Struct(_name, fields) | ReprStruct(_name, _, fields) => {
                 let mut output = String::new();
                 output.push_str("{ ");
                 for (idx, field) in fields.iter().enumerate() {
-                    if idx != 0 {
+                    // Zero-width bitfields are unnamed, and have no value
+                    if let BitField(0, _) = field {
+                        continue;
+                    }
+                    if output.len() > 2 {
                         output.push_str(", ");
                     }
                     let part = format!(".{} = {}", FIELD_NAMES[idx], self.c_val(field)?);
@@ -677,6 +1432,62 @@ impl CcAbiImpl {
                 output.push_str(" }");
                 output
             }
+            BitField(_, int_val) => self.c_val(&Int(int_val.clone()))?,
+            Union(name, active, fields) => {
+                let field = fields.get(*active).ok_or_else(|| {
+                    GenerateError::CUnsupported(format!("union {name} has no member {active}"))
+                })?;
+                format!("{{ .{} = {} }}", FIELD_NAMES[*active], self.c_val(field)?)
+            }
+            FnPtr(name, _, _) => format!("{name}_impl"),
+            DataEnum(name, repr, variants, active) => {
+                let (variant, fields) = variants.get(*active).ok_or_else(|| {
+                    GenerateError::CUnsupported(format!("enum {name} has no variant {active}"))
+                })?;
+                let mut field_vals = vec![];
+                if *repr != EnumRepr::C {
+                    field_vals.push(format!(".tag = {active}"));
+                }
+                for (idx, field) in fields.iter().enumerate() {
+                    field_vals.push(format!(".{} = {}", FIELD_NAMES[idx], self.c_val(field)?));
+                }
+                let variant_val = format!("{{ {} }}", field_vals.join(", "));
+                if *repr != EnumRepr::C {
+                    format!("{{ .{variant} = {variant_val} }}")
+                } else if fields.is_empty() {
+                    format!("{{ .tag = {name}_{variant} }}")
+                } else {
+                    format!("{{ .tag = {name}_{variant}, .payload = {{ .{variant} = {variant_val} }} }}")
+                }
+            }
+            Enum(name, _, variants, active) => {
+                let (variant, _) = variants.get(*active).ok_or_else(|| {
+                    GenerateError::CUnsupported(format!("enum {name} has no variant {active}"))
+                })?;
+                format!("{name}_{variant}")
+            }
+            Vector(vector_val) => {
+                // Vector extensions let us brace-initialize the lanes
+                let lanes = match vector_val {
+                    VectorVal::c__m128(lanes) => lanes
+                        .iter()
+                        .map(|&lane| self.c_val(&Float(FloatVal::c_float(lane))))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    VectorVal::c__m128i(lanes) => lanes
+                        .iter()
+                        .map(|&lane| self.c_val(&Int(c_int64_t(lane))))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    VectorVal::c__m256(lanes) => lanes
+                        .iter()
+                        .map(|&lane| self.c_val(&Float(FloatVal::c_float(lane))))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    VectorVal::c__m256d(lanes) => lanes
+                        .iter()
+                        .map(|&lane| self.c_val(&Float(FloatVal::c_double(lane))))
+                        .collect::<Result<Vec<_>, _>>()?,
+                };
+                format!("{{ {} }}", lanes.join(", "))
+            }
             Float(FloatVal::c_double(val)) => {
                 if val.fract() == 0.0 {
                     format!("{val}.0")
@@ -691,6 +1502,27 @@ impl CcAbiImpl {
                     format!("{val}f")
                 }
             }
+            Float(float_val) => {
+                // Convert from a double so the rounding is the same everywhere
+                let double_val = self.c_val(&Float(FloatVal::c_double(float_val.to_f64())))?;
+                format!("({}){double_val}", self.c_arg_type(val)?)
+            }
+            Complex(re, im) => {
+                let macro_name = match (re, im) {
+                    (FloatVal::c_double(_), FloatVal::c_double(_)) => "CMPLX",
+                    (FloatVal::c_float(_), FloatVal::c_float(_)) => "CMPLXF",
+                    _ => {
+                        return Err(GenerateError::CUnsupported(format!(
+                            "complex numbers must be made of two floats or two doubles"
+                        )))
+                    }
+                };
+                format!(
+                    "{macro_name}({}, {})",
+                    self.c_val(&Float(re.clone()))?,
+                    self.c_val(&Float(im.clone()))?
+                )
+            }
             Int(int_val) => match *int_val {
                 c__int128(val) => {
                     let lower = (val as u128) & 0x00000000_00000000_FFFFFFFF_FFFFFFFF;
@@ -710,6 +1542,31 @@ impl CcAbiImpl {
                 c_uint32_t(val) => format!("{val:#X}"),
                 c_uint16_t(val) => format!("{val:#X}"),
                 c_uint8_t(val) => format!("{val:#X}"),
+                // Explicit casts so the truncation on platforms where
+                // these are narrower than their value is well-defined.
+                c_char(val) => format!("(char){val}"),
+                c_schar(val) => format!("(signed char){val}"),
+                c_uchar(val) => format!("(unsigned char){val:#X}"),
+                c_int(val) => format!("(int){val}"),
+                c_uint(val) => format!("(unsigned int){val:#X}"),
+                c_long(val) => format!("(long){val}ll"),
+                c_BitInt {
+                    bits, ref value, ..
+                } => {
+                    // A bit-precise literal (C23), truncated to fit
+                    let bytes = bit_int_bytes(bits, false, value, bits.div_ceil(8) as u64);
+                    let hex = bytes
+                        .iter()
+                        .rev()
+                        .map(|byte| format!("{byte:02X}"))
+                        .collect::<String>();
+                    format!("(({})0x{hex}uwb)", self.c_arg_type(val)?)
+                }
+                c_ulong(val) => format!("(unsigned long){val}ull"),
+                c_size_t(val) => format!("(size_t){val}ull"),
+                c_ptrdiff_t(val) => format!("(ptrdiff_t){val}ll"),
+                c_intptr_t(val) => format!("(intptr_t){val}ll"),
+                c_uintptr_t(val) => format!("(uintptr_t){val}ull"),
             },
         };
         Ok(val)
@@ -748,28 +1605,128 @@ impl CcAbiImpl {
         is_var_root: bool,
     ) -> Result<Vec<String>, GenerateError> {
         let paths = match val {
-            Val::Int(_) | Val::Float(_) | Val::Bool(_) | Val::Ptr(_) => {
+            Val::Int(IntVal::c_BitInt { bits, .. }) => {
+                // Only the bytes that hold the value, the rest is padding
+                vec![format!("(*(uint8_t(*)[{}])&{from})", bits.div_ceil(8))]
+            }
+            Val::Float(FloatVal::c_long_double(_))
+                if self.target.long_double() == Some(LongDouble::X87) =>
+            {
+                // Only the first 10 bytes are the value, the rest is padding
+                vec![format!("(*(uint8_t(*)[10])&{from})")]
+            }
+            Val::Int(_)
+            | Val::Float(_)
+            | Val::Bool(_)
+            | Val::Ptr(_)
+            | Val::Vector(_)
+            | Val::Enum(..)
+            | Val::Atomic(_) => {
                 vec![format!("{from}")]
             }
-            Val::Struct(_name, fields) => {
+            Val::CStr(_) | Val::WideCStr(_) => {
+                // The contents (up to and including the NUL), as a variably
+                // modified array type so that sizeof is its length
+                if self.cc_flavor == CCFlavor::Msvc {
+                    return Err(GenerateError::CUnsupported(format!(
+                        "MSVC doesn't support variable length arrays, which checking strings needs"
+                    )));
+                }
+                let (ty, len) = if let Val::CStr(_) = val {
+                    ("char", "strlen")
+                } else {
+                    ("wchar_t", "wcslen")
+                };
+                vec![format!("(*({ty}(*)[{len}({from}) + 1]){from})")]
+            }
+            Val::Struct(_name, fields) | Val::ReprStruct(_name, _, fields) => {
+                let layout = if fields
+                    .iter()
+                    .any(|field| matches!(field, Val::BitField(..)))
+                {
+                    Some(
+                        bitfield_layout(fields, &self.target)
+                            .map_err(GenerateError::CUnsupported)?,
+                    )
+                } else {
+                    None
+                };
                 let mut paths = vec![];
                 for (idx, field) in fields.iter().enumerate() {
                     let base = format!("{from}.{}", FIELD_NAMES[idx]);
                     paths.extend(self.c_var_paths(field, &base, false)?);
+
+                    // After the last field of a run of bitfields, the raw bytes
+                    // of the run's storage are reported as a pseudo-field.
+                    let runs = layout.iter().flat_map(|layout| &layout.runs);
+                    for (_, bytes) in runs.filter(|(run, _)| run.end == idx + 1) {
+                        paths.push(format!(
+                            "(*(uint8_t(*)[{}])((char*)&{from} + {}))",
+                            bytes.end - bytes.start,
+                            bytes.start
+                        ));
+                    }
                 }
                 paths
             }
-            Val::Ref(pointee) => {
+            Val::Complex(re, _) => {
+                // Complex numbers are laid out like an array of the two parts
+                let part_ty = self.c_arg_type(&Val::Float(re.clone()))?;
+                vec![
+                    format!("((({part_ty}*)&{from})[0])"),
+                    format!("((({part_ty}*)&{from})[1])"),
+                ]
+            }
+            Val::BitField(0, _) => vec![],
+            Val::BitField(_, int_val) => {
+                // Can't take the address of a bitfield, so copy it out
+                let ty = self.c_arg_type(&Val::Int(int_val.clone()))?;
+                vec![format!("(({ty}){{{from}}})")]
+            }
+            Val::Union(name, active, fields) => {
+                // The size of the union is reported as a pseudo-field, followed
+                // by the fields of the active member.
+                let field = fields.get(*active).ok_or_else(|| {
+                    GenerateError::CUnsupported(format!("union {name} has no member {active}"))
+                })?;
+                let mut paths = vec![format!("((uint32_t){{sizeof({from})}})")];
+                let base = format!("{from}.{}", FIELD_NAMES[*active]);
+                paths.extend(self.c_var_paths(field, &base, false)?);
+                paths
+            }
+            Val::DataEnum(name, repr, variants, active) => {
+                // The size of the enum and its tag are reported as pseudo-fields,
+                // followed by the fields of the active variant.
+                let (variant, fields) = variants.get(*active).ok_or_else(|| {
+                    GenerateError::CUnsupported(format!("enum {name} has no variant {active}"))
+                })?;
+                let (tag, base) = if *repr == EnumRepr::C {
+                    (format!("{from}.tag"), format!("{from}.payload.{variant}"))
+                } else {
+                    (format!("{from}.{variant}.tag"), format!("{from}.{variant}"))
+                };
+                let mut paths = vec![format!("((uint32_t){{sizeof({from})}})"), tag];
+                for (idx, field) in fields.iter().enumerate() {
+                    let base = format!("{base}.{}", FIELD_NAMES[idx]);
+                    paths.extend(self.c_var_paths(field, &base, false)?);
+                }
+                paths
+            }
+            Val::Ref(pointee, _) => {
                 if is_var_root {
                     self.c_var_paths(pointee, from, false)?
-                } else if let Val::Array(_) = &**pointee {
+                } else if let Val::Array(..) = &**pointee {
                     self.c_var_paths(pointee, from, false)?
                 } else {
                     let base = format!("(*{from})");
                     self.c_var_paths(pointee, &base, false)?
                 }
             }
-            Val::Array(vals) => {
+            Val::RustType(_, val) | Val::Transparent(_, val) => {
+                self.c_var_paths(val, from, is_var_root)?
+            }
+            Val::EmptyArray(_) => vec![],
+            Val::Array(_, vals) | Val::FlexArray(_, vals) => {
                 let mut paths = vec![];
                 for (i, elem) in vals.iter().enumerate() {
                     let base = format!("{from}[{i}]");
@@ -777,6 +1734,16 @@ impl CcAbiImpl {
                 }
                 paths
             }
+            Val::FnPtr(_name, _, args) => {
+                // The pointer itself isn't interesting, the args it gets
+                // called with are (see write_c_fn_ptr_args).
+                let mut paths = vec![];
+                for (idx, arg) in args.iter().enumerate() {
+                    let base = format!("{from}_{}", ARG_NAMES[idx]);
+                    paths.extend(self.c_var_paths(arg, &base, true)?);
+                }
+                paths
+            }
         };
 
         Ok(paths)
@@ -789,7 +1756,7 @@ impl CcAbiImpl {
         use Val::*;
         use IntVal::*;
         match val {
-            Ref(x) => self.cfmt(x),
+            Ref(x, _) => self.cfmt(x),
             Ptr(_) => "\"p\"",
             Bool(_) => "\"d\"",
             Array(_) => {
@@ -817,3 +1784,43 @@ impl CcAbiImpl {
     }
     */
 }
+
+/// A C string literal for this string (which can't contain NULs).
+///
+/// Anything that isn't printable ASCII is escaped, with fixed-width escapes
+/// so that the characters that follow can't be mistaken for part of them.
+fn c_string_literal(string: &str, wide: bool) -> Result<String, GenerateError> {
+    let mut output = String::from(if wide { "L\"" } else { "\"" });
+    for ch in string.chars() {
+        if ch == '\0' {
+            return Err(GenerateError::CUnsupported(format!(
+                "strings can't contain NULs"
+            )));
+        }
+        match ch {
+            // `?` because of trigraphs
+            '"' | '\\' | '?' => output.push_str(&format!("\\{ch}")),
+            ' '..='~' => output.push(ch),
+            _ if wide && !ch.is_ascii() => {
+                let ch = ch as u32;
+                if ch < 0xA0 {
+                    // Universal character names can't name the C1 controls,
+                    // and the hex escape mustn't eat any hex digits after it.
+                    output.push_str(&format!("\\x{ch:X}\"\""));
+                } else if ch <= 0xFFFF {
+                    output.push_str(&format!("\\u{ch:04X}"));
+                } else {
+                    output.push_str(&format!("\\U{ch:08X}"));
+                }
+            }
+            _ => {
+                let mut buf = [0; 4];
+                for byte in ch.encode_utf8(&mut buf).bytes() {
+                    output.push_str(&format!("\\{byte:03o}"));
+                }
+            }
+        }
+    }
+    output.push('"');
+    Ok(output)
+}
@@ -5,9 +5,19 @@ pub static RUST_TEST_PREFIX: &str = include_str!("../../harness/rust_test_prefix
 
 static STRUCT_128: bool = false; // cfg!(target_arch="x86_64");
 
-#[allow(dead_code)]
 pub struct RustcAbiImpl {
     is_nightly: bool,
+    target: Target,
+}
+
+/// The byte array that stands in for a float type rustc doesn't have
+/// (on this target, or on this toolchain).
+struct StandIn {
+    name: &'static str,
+    size: usize,
+    align: usize,
+    /// The bytes of the value (which may be followed by padding)
+    bytes: Vec<u8>,
 }
 
 impl AbiImpl for RustcAbiImpl {
@@ -35,12 +45,15 @@ impl AbiImpl for RustcAbiImpl {
             CallingConvention::C => true,
             CallingConvention::Cdecl => true,
             CallingConvention::System => true,
-            CallingConvention::Win64 => true,
-            CallingConvention::Sysv64 => true,
+            CallingConvention::CUnwind => true,
+            CallingConvention::SystemUnwind => true,
+            CallingConvention::Win64 => self.target.is_x86_64(),
+            CallingConvention::Sysv64 => self.target.is_x86_64(),
             CallingConvention::Aapcs => true,
             CallingConvention::Stdcall => true,
             CallingConvention::Fastcall => true,
             CallingConvention::Vectorcall => false, // too experimental even for nightly use?
+            CallingConvention::Thiscall => self.target.is_x86(),
         }
     }
 
@@ -50,6 +63,17 @@ impl AbiImpl for RustcAbiImpl {
         test: &Test,
         convention: CallingConvention,
     ) -> Result<(), BuildError> {
+        // Defining functions that take vectors is fine, but declaring
+        // extern ones is still unstable.
+        if test.uses(|val| matches!(val, Val::Vector(_))) {
+            if !self.is_nightly {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "calling extern functions with SIMD vectors requires nightly (simd_ffi)"
+                ))
+                .into());
+            }
+            writeln!(f, "#![feature(simd_ffi)]")?;
+        }
         self.write_rust_prefix(f, test, convention)?;
         let convention_decl = self.rust_convention_decl(convention);
 
@@ -57,13 +81,18 @@ impl AbiImpl for RustcAbiImpl {
         writeln!(f, "extern \"{convention_decl}\" {{",)?;
         for function in &test.funcs {
             write!(f, "  ")?;
-            self.write_rust_signature(f, function)?;
+            self.write_rust_signature(f, function, convention, false)?;
             writeln!(f, ";")?;
         }
         writeln!(f, "}}")?;
         writeln!(f)?;
 
         // Now generate the body
+        write!(
+            f,
+            "{}",
+            self.rust_target_attr(&test.target_features(convention))
+        )?;
         writeln!(f, "#[no_mangle] pub extern \"C\" fn do_test() {{")?;
 
         for function in &test.funcs {
@@ -80,9 +109,21 @@ impl AbiImpl for RustcAbiImpl {
                     self.rust_var_decl(input, ARG_NAMES[idx])?,
                     self.rust_val(input)?
                 )?;
+                if let Val::FnPtr(_, _, args) = input.without_rust_type() {
+                    // We report the args that the callee should call it with
+                    self.write_rust_fn_ptr_args(f, args, ARG_NAMES[idx])?;
+                }
+            }
+            for (idx, input) in function.variadic_inputs.iter().enumerate() {
+                writeln!(
+                    f,
+                    "        {} = {};",
+                    self.rust_var_decl(input, ARG_NAMES[function.inputs.len() + idx])?,
+                    self.rust_val(input)?
+                )?;
             }
             writeln!(f)?;
-            for (idx, input) in function.inputs.iter().enumerate() {
+            for (idx, input) in function.all_inputs().enumerate() {
                 writeln!(
                     f,
                     "{}",
@@ -91,6 +132,15 @@ impl AbiImpl for RustcAbiImpl {
             }
             writeln!(f)?;
 
+            // Functions that unwind get called inside catch_unwind,
+            // and we report whether we caught anything as the output.
+            if function.callee_unwinds {
+                writeln!(
+                    f,
+                    "        let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {{"
+                )?;
+            }
+
             // Outputs
             write!(f, "        ")?;
             let pass_out = if let Some(output) = &function.output {
@@ -111,7 +161,22 @@ impl AbiImpl for RustcAbiImpl {
                 write!(f, "{}, ", self.rust_arg_pass(input, ARG_NAMES[idx])?)?;
             }
             if pass_out {
-                writeln!(f, "&mut {OUTPUT_NAME}")?;
+                match &function.output {
+                    Some(output @ Val::Ref(_, flavor)) if !flavor.is_default() => {
+                        write!(f, "{}, ", self.rust_arg_pass(output, OUTPUT_NAME)?)?;
+                    }
+                    _ => write!(f, "&mut {OUTPUT_NAME}, ")?,
+                }
+            }
+            // Rust won't implicitly promote variadic args like C does
+            for (idx, input) in function.variadic_inputs.iter().enumerate() {
+                let arg_name = ARG_NAMES[function.inputs.len() + idx];
+                let pass = self.rust_arg_pass(input, arg_name)?;
+                if let Val::Ref(..) = input {
+                    write!(f, "{pass}, ")?;
+                } else {
+                    write!(f, "{pass} as {}, ", self.rust_va_arg_type(input)?)?;
+                }
             }
             writeln!(f, ");")?;
             writeln!(f)?;
@@ -124,6 +189,14 @@ impl AbiImpl for RustcAbiImpl {
                     self.rust_write_val(output, "CALLER_OUTPUTS", OUTPUT_NAME, true)?
                 )?;
             }
+            if function.callee_unwinds {
+                writeln!(f, "        }})).is_err();")?;
+                writeln!(
+                    f,
+                    "{}",
+                    self.rust_write_val(&Val::Bool(true), "CALLER_OUTPUTS", "caught", true)?
+                )?;
+            }
 
             // Finished
             writeln!(
@@ -143,6 +216,16 @@ impl AbiImpl for RustcAbiImpl {
         test: &Test,
         convention: CallingConvention,
     ) -> Result<(), BuildError> {
+        // Calling variadic functions is fine, but defining them is still unstable.
+        if test.funcs.iter().any(|func| func.is_variadic()) {
+            if !self.is_nightly {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "defining variadic functions requires nightly (c_variadic)"
+                ))
+                .into());
+            }
+            writeln!(f, "#![feature(c_variadic)]")?;
+        }
         self.write_rust_prefix(f, test, convention)?;
         let convention_decl = self.rust_convention_decl(convention);
         for function in &test.funcs {
@@ -150,25 +233,85 @@ impl AbiImpl for RustcAbiImpl {
                 continue;
             }
             // Write the signature
+            write!(f, "{}", self.rust_target_attr(&function.target_features()))?;
             writeln!(f, "#[no_mangle]")?;
             write!(f, "pub unsafe extern \"{convention_decl}\" ")?;
-            self.write_rust_signature(f, function)?;
+            self.write_rust_signature(f, function, convention, true)?;
             writeln!(f, " {{")?;
 
             // Now the body
 
             // Report Inputs
             for (idx, input) in function.inputs.iter().enumerate() {
+                if let Val::FnPtr(name, _, args) = input.without_rust_type() {
+                    // The function reports these args for us
+                    self.write_rust_fn_ptr_args(f, args, ARG_NAMES[idx])?;
+                    if let Val::RustType(ty, _) = input {
+                        let arg_name = ARG_NAMES[idx];
+                        write!(
+                            f,
+                            "        core::mem::transmute::<{ty}, {name}>({arg_name})("
+                        )?;
+                    } else {
+                        write!(f, "        {}(", ARG_NAMES[idx])?;
+                    }
+                    for (arg_idx, arg) in args.iter().enumerate() {
+                        let arg_name = format!("{}_{}", ARG_NAMES[idx], ARG_NAMES[arg_idx]);
+                        write!(f, "{}, ", self.rust_arg_pass(arg, &arg_name)?)?;
+                    }
+                    writeln!(f, ");")?;
+                    continue;
+                }
                 writeln!(
                     f,
                     "{}",
                     self.rust_write_val(input, "CALLEE_INPUTS", ARG_NAMES[idx], false)?
                 )?;
+                if let Val::Ref(_, RefFlavor::Box | RefFlavor::OptionBox) = input {
+                    // The caller owns the pointee, so we mustn't free it
+                    writeln!(f, "        core::mem::forget({});", ARG_NAMES[idx])?;
+                }
+            }
+            for (idx, input) in function.variadic_inputs.iter().enumerate() {
+                let arg_name = ARG_NAMES[function.inputs.len() + idx];
+                let va_arg = format!("varargs.next_arg::<{}>()", self.rust_va_arg_type(input)?);
+                let unpromoted = match input {
+                    Val::Bool(_) => format!("{va_arg} != 0"),
+                    Val::Ref(..) => format!("&*{va_arg}"),
+                    _ => format!("{va_arg} as {}", self.rust_arg_type(input)?),
+                };
+                writeln!(
+                    f,
+                    "        let {} = {unpromoted};",
+                    self.rust_arg_decl(input, arg_name)?
+                )?;
+                writeln!(
+                    f,
+                    "{}",
+                    self.rust_write_val(input, "CALLEE_INPUTS", arg_name, false)?
+                )?;
             }
             writeln!(f)?;
 
-            // Report outputs and return
-            if let Some(output) = &function.output {
+            // Report outputs and return (or unwind)
+            if function.callee_unwinds {
+                writeln!(f, "        let unwound = true;")?;
+                writeln!(
+                    f,
+                    "{}",
+                    self.rust_write_val(&Val::Bool(true), "CALLEE_OUTPUTS", "unwound", true)?
+                )?;
+                writeln!(
+                    f,
+                    "        FINISHED_FUNC.unwrap()(CALLEE_INPUTS, CALLEE_OUTPUTS);"
+                )?;
+                // resume_unwind skips the panic hook, so nothing gets printed
+                writeln!(
+                    f,
+                    "        std::panic::resume_unwind(Box::new(\"{} is unwinding\"));",
+                    function.name
+                )?;
+            } else if let Some(output) = &function.output {
                 let decl = self.rust_var_decl(output, OUTPUT_NAME)?;
                 let val = self.rust_val(output)?;
                 writeln!(f, "        {decl} = {val};")?;
@@ -198,10 +341,23 @@ impl AbiImpl for RustcAbiImpl {
         Ok(())
     }
 
-    fn compile_callee(&self, src_path: &Path, lib_name: &str) -> Result<String, BuildError> {
-        let out = Command::new("rustc")
+    fn compile_callee(
+        &self,
+        _test: &Test,
+        src_path: &Path,
+        lib_name: &str,
+    ) -> Result<String, BuildError> {
+        // Crate names can't have dashes (which some conventions do).
+        let lib_name = lib_name.replace('-', "_");
+        let mut cmd = Command::new("rustc");
+        if !self.target.is_host() {
+            cmd.arg("--target").arg(&self.target.triple);
+        }
+        let out = cmd
             .arg("--crate-type")
             .arg("staticlib")
+            .arg("--crate-name")
+            .arg(&lib_name)
             .arg("--out-dir")
             .arg("target/temp/")
             .arg(src_path)
@@ -210,19 +366,25 @@ impl AbiImpl for RustcAbiImpl {
         if !out.status.success() {
             Err(BuildError::RustCompile(out))
         } else {
-            Ok(String::from(lib_name))
+            Ok(lib_name)
         }
     }
-    fn compile_caller(&self, src_path: &Path, lib_name: &str) -> Result<String, BuildError> {
+    fn compile_caller(
+        &self,
+        test: &Test,
+        src_path: &Path,
+        lib_name: &str,
+    ) -> Result<String, BuildError> {
         // Currently no need to be different
-        self.compile_callee(src_path, lib_name)
+        self.compile_callee(test, src_path, lib_name)
     }
 }
 
 impl RustcAbiImpl {
-    pub fn new(_system_info: &Config) -> Self {
+    pub fn new(system_info: &Config) -> Self {
         Self {
             is_nightly: built_info::RUSTC_VERSION.contains("nightly"),
+            target: system_info.target.clone(),
         }
     }
 
@@ -237,12 +399,24 @@ impl RustcAbiImpl {
             CallingConvention::C => "C",
             CallingConvention::Cdecl => "cdecl",
             CallingConvention::System => "system",
+            CallingConvention::CUnwind => "C-unwind",
+            CallingConvention::SystemUnwind => "system-unwind",
             CallingConvention::Win64 => "win64",
             CallingConvention::Sysv64 => "sysv64",
             CallingConvention::Aapcs => "aapcs",
             CallingConvention::Stdcall => "stdcall",
             CallingConvention::Fastcall => "fastcall",
             CallingConvention::Vectorcall => "vectorcall",
+            CallingConvention::Thiscall => "thiscall",
+        }
+    }
+
+    /// The attribute to enable the given target features on a function.
+    fn rust_target_attr(&self, features: &[&str]) -> String {
+        if features.is_empty() {
+            String::new()
+        } else {
+            format!("#[target_feature(enable = \"{}\")]\n", features.join(","))
         }
     }
 
@@ -257,13 +431,70 @@ impl RustcAbiImpl {
         if convention == CallingConvention::Vectorcall {
             writeln!(f, "#![feature(abi_vectorcall)]")?;
         }
+        if self.is_nightly {
+            let long_double_is_f128 = self.target.long_double() == Some(LongDouble::F128);
+            if test.uses(|val| matches!(val, Val::Float(FloatVal::c_Float16(_)))) {
+                writeln!(f, "#![feature(f16)]")?;
+            }
+            if test.uses(|val| {
+                matches!(val, Val::Float(FloatVal::c__float128(_)))
+                    || (long_double_is_f128
+                        && matches!(val, Val::Float(FloatVal::c_long_double(_))))
+            }) {
+                writeln!(f, "#![feature(f128)]")?;
+            }
+        }
         // Load test harness "headers"
         write!(f, "{}", RUST_TEST_PREFIX)?;
 
         // Forward-decl struct types
         let mut forward_decls = std::collections::HashMap::<String, String>::new();
         for function in &test.funcs {
-            for val in function.inputs.iter().chain(function.output.as_ref()) {
+            if !function.has_valid_fn_ptrs() {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "function pointers can only be used directly as args"
+                ))
+                .into());
+            }
+            if !function.has_valid_bitfields() {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "bitfields can only be fields of structs"
+                ))
+                .into());
+            }
+            if !function.has_valid_ref_flavors() {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "Ref flavors can only be used directly as inputs or outputs"
+                ))
+                .into());
+            }
+            if let Some(Val::Ref(_, flavor)) = &function.output {
+                if !flavor.can_be_output() {
+                    return Err(GenerateError::RustUnsupported(format!(
+                        "a {flavor:?} can't be an output (it's not a mutable borrow)"
+                    ))
+                    .into());
+                }
+            }
+            if !function.has_valid_flex_arrays() {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "flexible array members can only be the last field of a struct passed by Ref as an input"
+                ))
+                .into());
+            }
+            // Stand-ins have the right layout, but not the right passing rules
+            for val in function.all_inputs().chain(&function.output) {
+                if let Val::Ref(..) = val {
+                    continue;
+                }
+                if self.has_float_stand_in(val) {
+                    return Err(GenerateError::RustUnsupported(format!(
+                        "this float type can only be passed by reference (rustc doesn't have it here)"
+                    ))
+                    .into());
+                }
+            }
+            for val in function.all_inputs().chain(&function.output) {
                 for (name, decl) in self.rust_forward_decl(val)? {
                     match forward_decls.entry(name) {
                         std::collections::hash_map::Entry::Occupied(entry) => {
@@ -287,22 +518,66 @@ impl RustcAbiImpl {
         Ok(())
     }
 
-    fn write_rust_signature(&self, f: &mut dyn Write, function: &Func) -> Result<(), BuildError> {
+    /// Declare and initialize the args for a function pointer arg named
+    /// `arg_name` to be called with.
+    fn write_rust_fn_ptr_args(
+        &self,
+        f: &mut dyn Write,
+        args: &[Val],
+        arg_name: &str,
+    ) -> Result<(), BuildError> {
+        for (idx, arg) in args.iter().enumerate() {
+            let var_name = format!("{arg_name}_{}", ARG_NAMES[idx]);
+            writeln!(
+                f,
+                "        {} = {};",
+                self.rust_var_decl(arg, &var_name)?,
+                self.rust_val(arg)?
+            )?;
+        }
+        Ok(())
+    }
+
+    /// `is_definition` is whether this is the signature of the actual
+    /// impl of the function, rather than an extern declaration.
+    fn write_rust_signature(
+        &self,
+        f: &mut dyn Write,
+        function: &Func,
+        convention: CallingConvention,
+        is_definition: bool,
+    ) -> Result<(), BuildError> {
+        if function.is_variadic() {
+            if !convention.allows_variadics() {
+                return Err(GenerateError::UnsupportedConvention.into());
+            }
+            if function.inputs.is_empty() {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "variadic functions need at least one fixed input"
+                ))
+                .into());
+            }
+        }
+        let varargs = match (function.is_variadic(), is_definition) {
+            (false, _) => "",
+            (true, false) => "...",
+            (true, true) => "mut varargs: ...",
+        };
         write!(f, "fn {}(", function.name)?;
         for (idx, input) in function.inputs.iter().enumerate() {
             write!(f, "{}, ", self.rust_arg_decl(input, ARG_NAMES[idx])?)?;
         }
         if let Some(output) = &function.output {
             if let Some(out_param) = self.rust_out_param(output, OUT_PARAM_NAME)? {
-                write!(f, "{}", out_param)?;
-                write!(f, ")")?;
+                write!(f, "{}, ", out_param)?;
+                write!(f, "{varargs})")?;
             } else {
-                write!(f, ")")?;
+                write!(f, "{varargs})")?;
                 let ty = self.rust_arg_type(output)?;
                 write!(f, " -> {ty}")?;
             }
         } else {
-            write!(f, ")")?;
+            write!(f, "{varargs})")?;
         }
         Ok(())
     }
@@ -318,18 +593,68 @@ impl RustcAbiImpl {
     fn rust_forward_decl(&self, val: &Val) -> Result<Vec<(String, String)>, GenerateError> {
         use Val::*;
         match val {
-            Struct(name, fields) => {
+            Struct(name, fields) if fields.iter().any(|field| matches!(field, BitField(..))) => Ok(
+                vec![(name.clone(), self.rust_bitfield_struct_decl(name, fields)?)],
+            ),
+            Struct(name, fields) | ReprStruct(name, _, fields) => {
                 let mut results = vec![];
                 for field in fields.iter() {
                     results.extend(self.rust_forward_decl(field)?);
                 }
+                let repr = match val {
+                    ReprStruct(..) if fields.iter().any(|field| matches!(field, BitField(..))) => {
+                        return Err(GenerateError::RustUnsupported(format!(
+                            "bitfields in packed or aligned structs aren't supported"
+                        )));
+                    }
+                    ReprStruct(_, StructRepr::Packed(_), _)
+                        if fields.iter().any(|field| self.has_stand_in(field)) =>
+                    {
+                        return Err(GenerateError::RustUnsupported(format!(
+                            "packed structs can't contain stand-ins for types rustc doesn't have (they're repr(align))"
+                        )));
+                    }
+                    ReprStruct(_, StructRepr::Packed(n), _) => format!("C, packed({n})"),
+                    ReprStruct(_, StructRepr::Aligned(n), _) => format!("C, align({n})"),
+                    _ => format!("C"),
+                };
+                let mut output = String::new();
+                let ref_name = format!("{name}");
+                output.push_str(&format!("\n#[repr({repr})]\n"));
+                if let Some(FlexArray(..)) = fields.last() {
+                    // Generic over the length of the flexible array member, so the
+                    // side passing it in can allocate it (the default is the real type)
+                    output.push_str(&format!("pub struct {name}<const N: usize = 0> {{\n"));
+                } else {
+                    output.push_str(&format!("pub struct {name} {{\n"));
+                }
+                for (idx, field) in fields.iter().enumerate() {
+                    let ty = if let FlexArray(elem_ty, _) = field {
+                        format!("[{}; N]", self.rust_nested_type(elem_ty)?)
+                    } else {
+                        self.rust_nested_type(field)?
+                    };
+                    let line = format!("    {}: {ty},\n", FIELD_NAMES[idx]);
+                    output.push_str(&line);
+                }
+                output.push_str("}");
+                results.push((ref_name, output));
+                Ok(results)
+            }
+            Union(name, _, fields) => {
+                let mut results = vec![];
+                for field in fields.iter() {
+                    results.extend(self.rust_forward_decl(field)?);
+                }
+                // Union fields can't have drop glue, and our structs aren't Copy,
+                // so wrap every member in ManuallyDrop (which doesn't change layout).
                 let mut output = String::new();
                 let ref_name = format!("{name}");
                 output.push_str("\n#[repr(C)]\n");
-                output.push_str(&format!("pub struct {name} {{\n"));
+                output.push_str(&format!("pub union {name} {{\n"));
                 for (idx, field) in fields.iter().enumerate() {
                     let line = format!(
-                        "    {}: {},\n",
+                        "    {}: core::mem::ManuallyDrop<{}>,\n",
                         FIELD_NAMES[idx],
                         self.rust_nested_type(field)?
                     );
@@ -339,16 +664,311 @@ impl RustcAbiImpl {
                 results.push((ref_name, output));
                 Ok(results)
             }
-            Array(vals) => self.rust_forward_decl(&vals[0]),
-            Ref(pointee) => self.rust_forward_decl(pointee),
+            Enum(name, repr, variants, _) => {
+                if variants.is_empty() {
+                    return Err(GenerateError::RustUnsupported(format!(
+                        "enum {name} has no variants"
+                    )));
+                }
+                let mut output = String::new();
+                let ref_name = format!("{name}");
+                output.push_str(&format!("\n#[repr({})]\n", self.rust_enum_repr(*repr)));
+                output.push_str(&format!("pub enum {name} {{\n"));
+                for (variant, discriminant) in variants {
+                    output.push_str(&format!("    {variant} = {discriminant},\n"));
+                }
+                output.push_str("}");
+                Ok(vec![(ref_name, output)])
+            }
+            DataEnum(name, repr, variants, _) => {
+                if variants.is_empty() {
+                    return Err(GenerateError::RustUnsupported(format!(
+                        "enum {name} has no variants"
+                    )));
+                }
+                let mut results = vec![];
+                for (_, fields) in variants {
+                    for field in fields {
+                        results.extend(self.rust_forward_decl(field)?);
+                    }
+                }
+                let mut output = String::new();
+                let ref_name = format!("{name}");
+                output.push_str(&format!("\n#[repr({})]\n", self.rust_enum_repr(*repr)));
+                output.push_str(&format!("pub enum {name} {{\n"));
+                for (variant, fields) in variants {
+                    output.push_str(&format!("    {variant} {{ "));
+                    for (idx, field) in fields.iter().enumerate() {
+                        let ty = self.rust_nested_type(field)?;
+                        output.push_str(&format!("{}: {ty}, ", FIELD_NAMES[idx]));
+                    }
+                    output.push_str("},\n");
+                }
+                output.push_str("}");
+                // Also emit the tagged-union layout RFC 2195 specifies for this
+                // enum, which rust_place_paths reads it through so that a tag
+                // C disagrees on isn't UB.
+                for (variant, fields) in variants {
+                    // Fieldless variants don't need to be in the repr(C) union
+                    if *repr == EnumRepr::C && fields.is_empty() {
+                        continue;
+                    }
+                    output.push_str("\n#[repr(C)]\n");
+                    output.push_str(&format!("pub struct {name}_{variant} {{\n"));
+                    if *repr != EnumRepr::C {
+                        output.push_str(&format!("    tag: {},\n", self.rust_enum_repr(*repr)));
+                    }
+                    for (idx, field) in fields.iter().enumerate() {
+                        let ty = self.rust_nested_type(field)?;
+                        output.push_str(&format!("    {}: {ty},\n", FIELD_NAMES[idx]));
+                    }
+                    output.push_str("}");
+                }
+                if *repr == EnumRepr::C {
+                    // The tag is a C enum, so it's an int
+                    let has_payload = variants.iter().any(|(_, fields)| !fields.is_empty());
+                    if has_payload {
+                        output.push_str("\n#[repr(C)]\n");
+                        output.push_str(&format!("pub union {name}_Payload {{\n"));
+                        for (variant, fields) in variants {
+                            if !fields.is_empty() {
+                                output.push_str(&format!(
+                                    "    {variant}: core::mem::ManuallyDrop<{name}_{variant}>,\n"
+                                ));
+                            }
+                        }
+                        output.push_str("}");
+                    }
+                    output.push_str("\n#[repr(C)]\n");
+                    output.push_str(&format!("pub struct {name}_Repr {{\n"));
+                    output.push_str("    tag: core::ffi::c_int,\n");
+                    if has_payload {
+                        output.push_str(&format!("    payload: {name}_Payload,\n"));
+                    }
+                    output.push_str("}");
+                }
+                results.push((ref_name, output));
+                Ok(results)
+            }
+            FnPtr(name, convention, args) => {
+                let mut results = vec![];
+                for arg in args.iter() {
+                    results.extend(self.rust_forward_decl(arg)?);
+                }
+                if matches!(
+                    convention,
+                    CallingConvention::All | CallingConvention::Handwritten
+                ) || !self.supports_convention(*convention)
+                {
+                    return Err(GenerateError::UnsupportedConvention);
+                }
+                let convention_decl = self.rust_convention_decl(*convention);
+                let mut params = String::new();
+                for (idx, arg) in args.iter().enumerate() {
+                    params.push_str(&format!("{}, ", self.rust_arg_decl(arg, ARG_NAMES[idx])?));
+                }
+                // The type, and the function the caller passes in for it,
+                // which reports the args it gets called with.
+                let mut output = String::new();
+                output.push_str(&format!(
+                    "\npub type {name} = unsafe extern \"{convention_decl}\" fn({params});\n"
+                ));
+                output.push_str(&self.rust_target_attr(&target_features(args)));
+                output.push_str(&format!(
+                    "unsafe extern \"{convention_decl}\" fn {name}_impl({params}) {{\n"
+                ));
+                for (idx, arg) in args.iter().enumerate() {
+                    for path in self.rust_var_paths(arg, ARG_NAMES[idx], false)? {
+                        output.push_str(&format!("        WRITE.unwrap()(CALLEE_INPUTS, &{path} as *const _ as *const _, core::mem::size_of_val(&{path}) as u32);\n"));
+                    }
+                }
+                output.push_str("        FINISHED_VAL.unwrap()(CALLEE_INPUTS);\n");
+                output.push_str("}");
+                results.push((name.clone(), output));
+                Ok(results)
+            }
+            Int(IntVal::c_BitInt { bits, .. }) => {
+                let Some((size, align)) = self.rust_bit_int_stand_in(*bits)? else {
+                    return Ok(vec![]);
+                };
+                let name = self.rust_arg_type(val)?;
+                let mut output = String::new();
+                output.push_str(&format!("\n#[repr(C, align({align}))]\n"));
+                output.push_str(&format!("pub struct {name}([u8; {size}]);"));
+                Ok(vec![(name, output)])
+            }
+            Float(float_val) => {
+                let Some(stand_in) = self.rust_float_stand_in(float_val)? else {
+                    return Ok(vec![]);
+                };
+                let name = stand_in.name;
+                let len = stand_in.bytes.len();
+                let mut output = String::new();
+                output.push_str(&format!("\n#[repr(C, align({}))]\n", stand_in.align));
+                if stand_in.size > len {
+                    let padding = stand_in.size - len;
+                    output.push_str(&format!("pub struct {name}([u8; {len}], [u8; {padding}]);"));
+                } else {
+                    output.push_str(&format!("pub struct {name}([u8; {len}]);"));
+                }
+                Ok(vec![(name.to_owned(), output)])
+            }
+            Complex(..) => {
+                let name = self.rust_arg_type(val)?;
+                let part_ty = match name.as_str() {
+                    "ComplexF32" => "f32",
+                    _ => "f64",
+                };
+                let mut output = String::new();
+                output.push_str("\n#[repr(C)]\n");
+                output.push_str(&format!("pub struct {name} {{\n"));
+                output.push_str(&format!("    re: {part_ty},\n"));
+                output.push_str(&format!("    im: {part_ty},\n"));
+                output.push_str("}");
+                Ok(vec![(name, output)])
+            }
+            Array(elem_ty, _) => self.rust_forward_decl(elem_ty),
+            EmptyArray(elem_ty) | FlexArray(elem_ty, _) => self.rust_forward_decl(elem_ty),
+            Ref(pointee, _) | RustType(_, pointee) => self.rust_forward_decl(pointee),
+            Transparent(name, val) => {
+                let mut results = self.rust_forward_decl(val)?;
+                let ref_name = format!("{name}");
+                let output = format!(
+                    "\n#[repr(transparent)]\npub struct {name}(pub {});",
+                    self.rust_nested_type(val)?
+                );
+                results.push((ref_name, output));
+                Ok(results)
+            }
             _ => Ok(vec![]),
         }
     }
 
+    /// The forward-declaration of a struct with bitfields.
+    ///
+    /// Rust doesn't have bitfields, so each run of them is stored as the
+    /// bytes C would put them in (see bitfield_layout), padded to the right
+    /// place. The struct gets a `new` that writes the bitfields into the
+    /// storage, and a method for each bitfield that reads it back out.
+    fn rust_bitfield_struct_decl(
+        &self,
+        name: &str,
+        fields: &[Val],
+    ) -> Result<String, GenerateError> {
+        if self.target.is_big_endian() {
+            return Err(GenerateError::RustUnsupported(format!(
+                "bitfields are only implemented for little-endian targets"
+            )));
+        }
+        let layout =
+            bitfield_layout(fields, &self.target).map_err(GenerateError::RustUnsupported)?;
+
+        let mut decl_fields = String::new();
+        let mut init_fields = String::new();
+        let mut params = String::new();
+        let mut writes = String::new();
+        let mut reads = String::new();
+        // Explicit padding to get from `pos` to `offset`, if needed
+        let mut pos = 0;
+        let mut pads = 0;
+        let mut pad = |pos: u64, offset: u64| {
+            if offset <= pos {
+                return (String::new(), String::new());
+            }
+            pads += 1;
+            let len = offset - pos;
+            (
+                format!("    _pad{pads}: [u8; {len}],\n"),
+                format!("_pad{pads}: [0; {len}], "),
+            )
+        };
+        for (idx, field) in fields.iter().enumerate() {
+            let field_name = FIELD_NAMES[idx];
+            let Val::BitField(width, int_val) = field else {
+                let offset = layout.offsets[idx] / 8;
+                let (decl, init) = pad(pos, offset);
+                decl_fields.push_str(&decl);
+                init_fields.push_str(&init);
+                let ty = self.rust_nested_type(field)?;
+                decl_fields.push_str(&format!("    {field_name}: {ty},\n"));
+                init_fields.push_str(&format!("{field_name}, "));
+                params.push_str(&format!("{field_name}: {ty}, "));
+                pos = offset + layout.sizes[idx];
+                continue;
+            };
+            if let Some((run, bytes)) = layout.runs.iter().find(|(run, _)| run.start == idx) {
+                let (decl, init) = pad(pos, bytes.start);
+                decl_fields.push_str(&decl);
+                init_fields.push_str(&init);
+                let len = bytes.end - bytes.start;
+                decl_fields.push_str(&format!("    bitfields{}: [u8; {len}],\n", run.start));
+                init_fields.push_str(&format!("bitfields{}: [0; {len}], ", run.start));
+                pos = bytes.end;
+            }
+            if *width == 0 {
+                continue;
+            }
+            let (run, bytes) = layout
+                .runs
+                .iter()
+                .find(|(run, _)| run.contains(&idx))
+                .expect("named bitfield wasn't in a run");
+            let storage = format!("bitfields{}", run.start);
+            let offset = layout.offsets[idx] - bytes.start * 8;
+            let ty = self.rust_arg_type(&Val::Int(int_val.clone()))?;
+            params.push_str(&format!("{field_name}: {ty}, "));
+            writes.push_str(&format!(
+                "        write_bits(&mut out.{storage}, {offset}, {width}, {field_name} as u64);\n"
+            ));
+            reads.push_str(&format!("    fn {field_name}(&self) -> {ty} {{\n"));
+            reads.push_str(&format!(
+                "        let bits = read_bits(&self.{storage}, {offset}, {width});\n"
+            ));
+            if int_val.is_signed(&self.target) {
+                // Sign-extend
+                let shift = 64 - width;
+                reads.push_str(&format!(
+                    "        ((bits << {shift}) as i64 >> {shift}) as {ty}\n"
+                ));
+            } else {
+                reads.push_str(&format!("        bits as {ty}\n"));
+            }
+            reads.push_str("    }\n");
+        }
+        let (decl, init) = pad(pos, layout.end);
+        decl_fields.push_str(&decl);
+        init_fields.push_str(&init);
+
+        let mut output = String::new();
+        output.push_str(&format!("\n#[repr(C, align({}))]\n", layout.align));
+        output.push_str(&format!("pub struct {name} {{\n{decl_fields}}}\n"));
+        output.push_str(&format!("impl {name} {{\n"));
+        output.push_str(&format!("    fn new({params}) -> Self {{\n"));
+        output.push_str(&format!("        let mut out = Self {{ {init_fields}}};\n"));
+        output.push_str(&writes);
+        output.push_str("        out\n");
+        output.push_str("    }\n");
+        output.push_str(&reads);
+        output.push_str("}");
+        Ok(output)
+    }
+
     /// The decl to use for a local var (reference-ness stripped)
     fn rust_var_decl(&self, val: &Val, var_name: &str) -> Result<String, GenerateError> {
-        if let Val::Ref(pointee) = val {
-            Ok(self.rust_var_decl(pointee, var_name)?)
+        if let Val::Ref(pointee, flavor) = val {
+            let decl = self.rust_var_decl(pointee, var_name)?;
+            if flavor.is_default() {
+                Ok(decl)
+            } else {
+                // Non-default flavors are all made from a `*mut T` to it
+                Ok(decl.replacen("let ", "let mut ", 1))
+            }
+        } else if let Some(len) = flex_array_len(val) {
+            // Allocate the flexible array member's elements along with it
+            Ok(format!(
+                "let {var_name}: {}<{len}>",
+                self.rust_arg_type(val)?
+            ))
         } else {
             Ok(format!("let {var_name}: {}", self.rust_arg_type(val)?))
         }
@@ -356,7 +976,7 @@ impl RustcAbiImpl {
 
     /// The decl to use for a function arg (apply referenceness)
     fn rust_arg_decl(&self, val: &Val, arg_name: &str) -> Result<String, GenerateError> {
-        if let Val::Ref(pointee) = val {
+        if let Val::Ref(pointee, RefFlavor::Default) = val {
             Ok(format!("{arg_name}: &{}", self.rust_arg_type(pointee)?))
         } else {
             Ok(format!("{arg_name}: {}", self.rust_arg_type(val)?))
@@ -369,11 +989,16 @@ impl RustcAbiImpl {
         val: &Val,
         out_param_name: &str,
     ) -> Result<Option<String>, GenerateError> {
-        if let Val::Ref(pointee) = val {
+        if let Val::Ref(pointee, RefFlavor::Default) = val {
             Ok(Some(format!(
                 "{out_param_name}: &mut {}",
                 self.rust_arg_type(pointee)?
             )))
+        } else if let Val::Ref(..) = val {
+            Ok(Some(format!(
+                "{out_param_name}: {}",
+                self.rust_arg_type(val)?
+            )))
         } else {
             Ok(None)
         }
@@ -385,7 +1010,7 @@ impl RustcAbiImpl {
         val: &Val,
         output_name: &str,
     ) -> Result<Option<String>, GenerateError> {
-        if let Val::Ref(pointee) = val {
+        if let Val::Ref(pointee, _) = val {
             Ok(Some(format!(
                 "let mut {output_name}: {} = {};",
                 self.rust_arg_type(pointee)?,
@@ -398,8 +1023,22 @@ impl RustcAbiImpl {
 
     /// How to pass an argument
     fn rust_arg_pass(&self, val: &Val, arg_name: &str) -> Result<String, GenerateError> {
-        if let Val::Ref(_) = val {
-            Ok(format!("&{arg_name}"))
+        if let Val::Ref(pointee, flavor) = val {
+            let ty = self.rust_arg_type(pointee)?;
+            if flavor.is_default() {
+                if let Some(len) = flex_array_len(pointee) {
+                    // Forget the length of the flexible array member again
+                    Ok(format!(
+                        "&*(&{arg_name} as *const {ty}<{len}> as *const {ty})"
+                    ))
+                } else {
+                    Ok(format!("&{arg_name}"))
+                }
+            } else {
+                // Forgetting the length of any flexible array member on the way
+                let ptr = format!("(&mut {arg_name} as *mut _ as *mut {ty})");
+                Ok(rust_ref_from_ptr(*flavor, &ptr))
+            }
         } else {
             Ok(format!("{arg_name}"))
         }
@@ -412,8 +1051,15 @@ impl RustcAbiImpl {
         var_name: &str,
         out_param_name: &str,
     ) -> Result<String, GenerateError> {
-        if let Val::Ref(_) = val {
+        if let Val::Ref(_, RefFlavor::Default) = val {
             Ok(format!("*{out_param_name} = {var_name};"))
+        } else if let Val::Ref(_, RefFlavor::OptionRefMut) = val {
+            Ok(format!("*{out_param_name}.unwrap() = {var_name};"))
+        } else if let Val::Ref(_, flavor) = val {
+            Ok(format!(
+                "{} = {var_name};",
+                rust_ref_deref(*flavor, out_param_name)
+            ))
         } else {
             Ok(format!("return {var_name};"))
         }
@@ -424,13 +1070,98 @@ impl RustcAbiImpl {
         use IntVal::*;
         use Val::*;
         let out = match val {
-            Ref(pointee) => format!("*mut {}", self.rust_arg_type(pointee)?),
+            Ref(pointee, flavor) => {
+                let ty = self.rust_arg_type(pointee)?;
+                match flavor {
+                    RefFlavor::Default | RefFlavor::MutPtr => format!("*mut {ty}"),
+                    RefFlavor::Ref => format!("&{ty}"),
+                    RefFlavor::RefMut => format!("&mut {ty}"),
+                    RefFlavor::OptionRef => format!("Option<&{ty}>"),
+                    RefFlavor::OptionRefMut => format!("Option<&mut {ty}>"),
+                    RefFlavor::NonNull => format!("core::ptr::NonNull<{ty}>"),
+                    RefFlavor::Box => format!("Box<{ty}>"),
+                    RefFlavor::OptionBox => format!("Option<Box<{ty}>>"),
+                    RefFlavor::ConstPtr => format!("*const {ty}"),
+                }
+            }
+            RustType(ty, val) => {
+                // These are transmuted from (and reported like) the value
+                let is_scalar = match val.as_ref() {
+                    Int(IntVal::c_BitInt { .. }) => false,
+                    Float(float_val) => self.rust_float_stand_in(float_val)?.is_none(),
+                    Int(_) | Bool(_) | Ptr(_) | FnPtr(..) => true,
+                    _ => false,
+                };
+                if !is_scalar {
+                    return Err(GenerateError::RustUnsupported(format!(
+                        "RustType can only be an int, float, bool, or pointer (not {})",
+                        self.rust_arg_type(val)?
+                    )));
+                }
+                format!("{ty}")
+            }
             Ptr(_) => format!("*mut ()"),
+            CStr(_) => format!("*const core::ffi::c_char"),
+            WideCStr(_) => format!("*const {}", self.rust_wchar_t()),
+            Atomic(int_val) => {
+                let (atomic, _) = self.rust_atomic_type(int_val)?;
+                format!("core::sync::atomic::{atomic}")
+            }
             Bool(_) => format!("bool"),
-            Array(vals) => format!("[{}; {}]", self.rust_arg_type(&vals[0])?, vals.len()),
-            Struct(name, _) => format!("{name}"),
-            Float(FloatVal::c_double(_)) => format!("f64"),
-            Float(FloatVal::c_float(_)) => format!("f32"),
+            Array(elem_ty, vals) => format!("[{}; {}]", self.rust_arg_type(elem_ty)?, vals.len()),
+            EmptyArray(elem_ty) | FlexArray(elem_ty, _) => {
+                format!("[{}; 0]", self.rust_arg_type(elem_ty)?)
+            }
+            Struct(name, _) | ReprStruct(name, _, _) => format!("{name}"),
+            Union(name, _, _) => format!("{name}"),
+            Enum(name, _, _, _) | DataEnum(name, _, _, _) | Transparent(name, _) => {
+                format!("{name}")
+            }
+            FnPtr(name, _, _) => format!("{name}"),
+            BitField(..) => {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "bitfields can only be fields of structs"
+                )));
+            }
+            Vector(vector_val) => {
+                if !self.target.is_x86_64() {
+                    return Err(GenerateError::RustUnsupported(format!(
+                        "SIMD vectors are only implemented for x86_64"
+                    )));
+                }
+                match vector_val {
+                    VectorVal::c__m128(_) => format!("core::arch::x86_64::__m128"),
+                    VectorVal::c__m128i(_) => format!("core::arch::x86_64::__m128i"),
+                    VectorVal::c__m256(_) => format!("core::arch::x86_64::__m256"),
+                    VectorVal::c__m256d(_) => format!("core::arch::x86_64::__m256d"),
+                }
+            }
+            Float(float_val) => {
+                if let Some(stand_in) = self.rust_float_stand_in(float_val)? {
+                    format!("{}", stand_in.name)
+                } else {
+                    match float_val {
+                        FloatVal::c_double(_) => format!("f64"),
+                        FloatVal::c_float(_) => format!("f32"),
+                        FloatVal::c_Float16(_) => format!("f16"),
+                        FloatVal::c__float128(_) => format!("f128"),
+                        FloatVal::c_long_double(_) => match self.target.long_double() {
+                            Some(LongDouble::F128) => format!("f128"),
+                            _ => format!("f64"),
+                        },
+                        FloatVal::c__bf16(_) => unreachable!("bf16 always has a stand-in"),
+                    }
+                }
+            }
+            Complex(re, im) => match (re, im) {
+                (FloatVal::c_double(_), FloatVal::c_double(_)) => format!("ComplexF64"),
+                (FloatVal::c_float(_), FloatVal::c_float(_)) => format!("ComplexF32"),
+                _ => {
+                    return Err(GenerateError::RustUnsupported(format!(
+                        "complex numbers must be made of two floats or two doubles"
+                    )))
+                }
+            },
             Int(int_val) => match int_val {
                 c__int128(_) => {
                     if STRUCT_128 {
@@ -454,11 +1185,231 @@ impl RustcAbiImpl {
                 c_uint32_t(_) => format!("u32"),
                 c_uint16_t(_) => format!("u16"),
                 c_uint8_t(_) => format!("u8"),
+                c_char(_) => format!("core::ffi::c_char"),
+                c_schar(_) => format!("core::ffi::c_schar"),
+                c_uchar(_) => format!("core::ffi::c_uchar"),
+                c_int(_) => format!("core::ffi::c_int"),
+                c_uint(_) => format!("core::ffi::c_uint"),
+                c_long(_) => format!("core::ffi::c_long"),
+                c_ulong(_) => format!("core::ffi::c_ulong"),
+                c_size_t(_) => format!("usize"),
+                c_ptrdiff_t(_) => format!("isize"),
+                c_intptr_t(_) => format!("isize"),
+                c_uintptr_t(_) => format!("usize"),
+                c_BitInt { bits, signed, .. } => {
+                    if self.rust_bit_int_stand_in(*bits)?.is_some() {
+                        format!("BitInt{bits}")
+                    } else if *signed {
+                        format!("i{bits}")
+                    } else {
+                        format!("u{bits}")
+                    }
+                }
             },
         };
         Ok(out)
     }
 
+    /// The type to pass this value as when it's a variadic arg, after
+    /// the default argument promotions.
+    fn rust_va_arg_type(&self, val: &Val) -> Result<String, GenerateError> {
+        use IntVal::*;
+        use Val::*;
+        let out = match val {
+            Bool(_) => format!("core::ffi::c_int"),
+            Float(FloatVal::c_float(_)) => format!("f64"),
+            Int(c_int8_t(_) | c_int16_t(_) | c_uint8_t(_) | c_uint16_t(_)) => {
+                format!("core::ffi::c_int")
+            }
+            Int(c_char(_) | c_schar(_) | c_uchar(_)) => format!("core::ffi::c_int"),
+            Int(c__int128(_) | c__uint128(_)) => {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "128-bit ints can't be passed as variadic args"
+                )))
+            }
+            Int(c_BitInt { .. }) => {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "_BitInts can't be passed as variadic args"
+                )))
+            }
+            Ref(pointee, _) if matches!(**pointee, Array(..)) => {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "arrays can't be passed as variadic args"
+                )))
+            }
+            Int(_) | Float(FloatVal::c_double(_)) | Ptr(_) | Ref(..) | CStr(_) | WideCStr(_) => {
+                self.rust_arg_type(val)?
+            }
+            Float(_) => {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "extended floats can't be passed as variadic args"
+                )))
+            }
+            Array(..) | Struct(..) | ReprStruct(..) | Union(..) | Enum(..) | DataEnum(..)
+            | Vector(_) | Complex(..) | FnPtr(..) | BitField(..) | EmptyArray(_)
+            | FlexArray(..) | Atomic(_) | RustType(..) | Transparent(..) => {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "only scalars and Refs can be passed as variadic args"
+                )))
+            }
+        };
+        Ok(out)
+    }
+
+    /// Whether this value contains a float that needs a stand-in.
+    fn has_float_stand_in(&self, val: &Val) -> bool {
+        let mut found = false;
+        val.visit(&mut |val| {
+            if let Val::Float(float_val) = val {
+                found |= matches!(self.rust_float_stand_in(float_val), Ok(Some(_)));
+            }
+        });
+        found
+    }
+
+    /// Whether this value contains anything that needs a stand-in.
+    fn has_stand_in(&self, val: &Val) -> bool {
+        let mut found = self.has_float_stand_in(val);
+        val.visit(&mut |val| {
+            if let Val::Int(IntVal::c_BitInt { bits, .. }) = val {
+                found |= matches!(self.rust_bit_int_stand_in(*bits), Ok(Some(_)));
+            }
+        });
+        found
+    }
+
+    /// The core::sync::atomic type for this int, and the int type it holds.
+    fn rust_atomic_type(
+        &self,
+        int_val: &IntVal,
+    ) -> Result<(&'static str, &'static str), GenerateError> {
+        use IntVal::*;
+        match int_val {
+            c_size_t(_) | c_uintptr_t(_) => return Ok(("AtomicUsize", "usize")),
+            c_ptrdiff_t(_) | c_intptr_t(_) => return Ok(("AtomicIsize", "isize")),
+            c__int128(_) | c__uint128(_) | c_BitInt { .. } => {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "Rust doesn't have atomics of this size"
+                )))
+            }
+            _ => {}
+        }
+        // (c_char is unsigned on some targets)
+        let signed = int_val.is_signed(&self.target);
+        let size = int_val.size(&self.target).unwrap();
+        Ok(match (signed, size) {
+            (true, 1) => ("AtomicI8", "i8"),
+            (true, 2) => ("AtomicI16", "i16"),
+            (true, 4) => ("AtomicI32", "i32"),
+            (true, _) => ("AtomicI64", "i64"),
+            (false, 1) => ("AtomicU8", "u8"),
+            (false, 2) => ("AtomicU16", "u16"),
+            (false, 4) => ("AtomicU32", "u32"),
+            (false, _) => ("AtomicU64", "u64"),
+        })
+    }
+
+    /// The Rust equivalent of wchar_t (which core::ffi doesn't have).
+    fn rust_wchar_t(&self) -> &'static str {
+        if self.target.is_windows() {
+            "u16"
+        } else {
+            "u32"
+        }
+    }
+
+    /// The size and alignment of the byte array standing in for
+    /// `_BitInt(bits)`, if it isn't the width of a native int.
+    fn rust_bit_int_stand_in(&self, bits: u32) -> Result<Option<(u64, u64)>, GenerateError> {
+        if matches!(bits, 8 | 16 | 32 | 64 | 128) {
+            return Ok(None);
+        }
+        match self.target.bit_int_layout(bits) {
+            Some(layout) => Ok(Some(layout)),
+            None => Err(GenerateError::RustUnsupported(format!(
+                "the layout of _BitInt on this target isn't known"
+            ))),
+        }
+    }
+
+    /// The stand-in for this float type, if rustc doesn't have it.
+    fn rust_float_stand_in(&self, float_val: &FloatVal) -> Result<Option<StandIn>, GenerateError> {
+        let is_x86 = self.target.is_x86() || self.target.is_x86_64();
+        let val = float_val.to_f64();
+        let to_bytes = |bits: u128, len: usize| {
+            if self.target.is_big_endian() {
+                bits.to_be_bytes()[16 - len..].to_vec()
+            } else {
+                bits.to_le_bytes()[..len].to_vec()
+            }
+        };
+        let stand_in = match float_val {
+            FloatVal::c_float(_) | FloatVal::c_double(_) => None,
+            FloatVal::c_Float16(_) if self.is_nightly => None,
+            FloatVal::c_Float16(_) => Some(StandIn {
+                name: "StandInFloat16",
+                size: 2,
+                align: 2,
+                bytes: to_bytes(f64_to_float_bits(val, 5, 10), 2),
+            }),
+            FloatVal::c__float128(_) if !is_x86 => {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "__float128 is only available on x86"
+                )))
+            }
+            FloatVal::c__float128(_) if self.is_nightly => None,
+            FloatVal::c__float128(_) => Some(StandIn {
+                name: "StandInFloat128",
+                size: 16,
+                align: 16,
+                bytes: to_bytes(f64_to_float_bits(val, 15, 112), 16),
+            }),
+            FloatVal::c__bf16(_) => Some(StandIn {
+                name: "StandInBf16",
+                size: 2,
+                align: 2,
+                bytes: to_bytes(f64_to_float_bits(val, 8, 7), 2),
+            }),
+            FloatVal::c_long_double(_) => match self.target.long_double() {
+                Some(LongDouble::F64) => None,
+                Some(LongDouble::F128) if self.is_nightly => None,
+                Some(LongDouble::F128) => Some(StandIn {
+                    name: "StandInLongDouble",
+                    size: 16,
+                    align: 16,
+                    bytes: to_bytes(f64_to_float_bits(val, 15, 112), 16),
+                }),
+                Some(LongDouble::X87) => Some(StandIn {
+                    name: "StandInLongDouble",
+                    size: if self.target.is_x86() { 12 } else { 16 },
+                    align: if self.target.is_x86() { 4 } else { 16 },
+                    bytes: to_bytes(f64_to_x87_bits(val), 10),
+                }),
+                None => {
+                    return Err(GenerateError::RustUnsupported(format!(
+                        "the format of long double on this target isn't known"
+                    )))
+                }
+            },
+        };
+        Ok(stand_in)
+    }
+
+    /// The argument to `#[repr(..)]` for an enum with this repr.
+    fn rust_enum_repr(&self, repr: EnumRepr) -> &'static str {
+        match repr {
+            EnumRepr::C => "C",
+            EnumRepr::u8 => "u8",
+            EnumRepr::u16 => "u16",
+            EnumRepr::u32 => "u32",
+            EnumRepr::u64 => "u64",
+            EnumRepr::i8 => "i8",
+            EnumRepr::i16 => "i16",
+            EnumRepr::i32 => "i32",
+            EnumRepr::i64 => "i64",
+        }
+    }
+
     /// The type name to use for this value when it is stored in composite.
     ///
     /// This is separated out in case there's a type that needs different
@@ -473,10 +1424,58 @@ impl RustcAbiImpl {
         use IntVal::*;
         use Val::*;
         let out = match val {
-            Ref(pointee) => self.rust_val(pointee)?,
-            Ptr(addr) => format!("{addr:#X} as *mut ()"),
+            Ref(pointee, _) => self.rust_val(pointee)?,
+            RustType(ty, val) => format!(
+                "core::mem::transmute::<{}, {ty}>({})",
+                self.rust_arg_type(val)?,
+                self.rust_val(val)?
+            ),
+            Transparent(name, val) => format!("{name}({})", self.rust_val(val)?),
+            Ptr(addr) => {
+                let addr = self.target.truncate_ptr(*addr);
+                format!("{addr:#X} as *mut ()")
+            }
+            Atomic(int_val) => {
+                let (atomic, int_ty) = self.rust_atomic_type(int_val)?;
+                let int_val = self.rust_val(&Int(int_val.clone()))?;
+                format!("core::sync::atomic::{atomic}::new(({int_val}) as {int_ty})")
+            }
+            CStr(string) => {
+                if string.contains('\0') {
+                    return Err(GenerateError::RustUnsupported(format!(
+                        "strings can't contain NULs"
+                    )));
+                }
+                let escaped = string
+                    .bytes()
+                    .flat_map(std::ascii::escape_default)
+                    .map(char::from)
+                    .collect::<String>();
+                format!("b\"{escaped}\\0\".as_ptr() as *const core::ffi::c_char")
+            }
+            WideCStr(string) => {
+                if string.contains('\0') {
+                    return Err(GenerateError::RustUnsupported(format!(
+                        "strings can't contain NULs"
+                    )));
+                }
+                let wchar_t = self.rust_wchar_t();
+                let units: Vec<u32> = if wchar_t == "u16" {
+                    string.encode_utf16().map(u32::from).collect()
+                } else {
+                    string.chars().map(u32::from).collect()
+                };
+                let units = units
+                    .iter()
+                    .map(|unit| format!("{unit:#X}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                // An explicit borrow of a constant, so it's promoted to a static
+                format!("(&[{units}, 0{wchar_t}]).as_ptr()")
+            }
             Bool(val) => format!("{val}"),
-            Array(vals) => {
+            EmptyArray(_) => format!("[]"),
+            Array(_, vals) | FlexArray(_, vals) => {
                 let mut output = String::new();
                 output.push_str(&format!("[",));
                 for elem in vals {
@@ -486,7 +1485,17 @@ impl RustcAbiImpl {
                 output.push_str("]");
                 output
             }
-            Struct(name, fields) => {
+            Struct(name, fields) if fields.iter().any(|field| matches!(field, BitField(..))) => {
+                // Zero-width bitfields have no value to pass along
+                let args = fields
+                    .iter()
+                    .filter(|field| !matches!(field, BitField(0, _)))
+                    .map(|field| self.rust_val(field))
+                    .collect::<Result<Vec<_>, _>>()?;
+                format!("{name}::new({})", args.join(", "))
+            }
+            BitField(_, int_val) => self.rust_val(&Int(int_val.clone()))?,
+            Struct(name, fields) | ReprStruct(name, _, fields) => {
                 let mut output = String::new();
                 output.push_str(&format!("{name} {{ "));
                 for (idx, field) in fields.iter().enumerate() {
@@ -496,6 +1505,78 @@ impl RustcAbiImpl {
                 output.push_str(" }");
                 output
             }
+            Union(name, active, fields) => {
+                let field = fields.get(*active).ok_or_else(|| {
+                    GenerateError::RustUnsupported(format!("union {name} has no member {active}"))
+                })?;
+                format!(
+                    "{name} {{ {}: core::mem::ManuallyDrop::new({}) }}",
+                    FIELD_NAMES[*active],
+                    self.rust_val(field)?
+                )
+            }
+            Enum(name, _, variants, active) => {
+                let (variant, _) = variants.get(*active).ok_or_else(|| {
+                    GenerateError::RustUnsupported(format!("enum {name} has no variant {active}"))
+                })?;
+                format!("{name}::{variant}")
+            }
+            DataEnum(name, _, variants, active) => {
+                let (variant, fields) = variants.get(*active).ok_or_else(|| {
+                    GenerateError::RustUnsupported(format!("enum {name} has no variant {active}"))
+                })?;
+                let mut output = String::new();
+                output.push_str(&format!("{name}::{variant} {{ "));
+                for (idx, field) in fields.iter().enumerate() {
+                    let part = format!("{}: {},", FIELD_NAMES[idx], self.rust_val(field)?);
+                    output.push_str(&part);
+                }
+                output.push_str(" }");
+                output
+            }
+            FnPtr(name, _, _) => format!("{name}_impl"),
+            Vector(vector_val) => {
+                // There's no literal syntax for these, so transmute the lanes
+                let (lanes, lane_ty, len) = match vector_val {
+                    VectorVal::c__m128(lanes) => (
+                        lanes
+                            .iter()
+                            .map(|&lane| self.rust_val(&Float(FloatVal::c_float(lane))))
+                            .collect::<Result<Vec<_>, _>>()?,
+                        "f32",
+                        lanes.len(),
+                    ),
+                    VectorVal::c__m128i(lanes) => (
+                        lanes
+                            .iter()
+                            .map(|&lane| self.rust_val(&Int(c_int64_t(lane))))
+                            .collect::<Result<Vec<_>, _>>()?,
+                        "i64",
+                        lanes.len(),
+                    ),
+                    VectorVal::c__m256(lanes) => (
+                        lanes
+                            .iter()
+                            .map(|&lane| self.rust_val(&Float(FloatVal::c_float(lane))))
+                            .collect::<Result<Vec<_>, _>>()?,
+                        "f32",
+                        lanes.len(),
+                    ),
+                    VectorVal::c__m256d(lanes) => (
+                        lanes
+                            .iter()
+                            .map(|&lane| self.rust_val(&Float(FloatVal::c_double(lane))))
+                            .collect::<Result<Vec<_>, _>>()?,
+                        "f64",
+                        lanes.len(),
+                    ),
+                };
+                format!(
+                    "core::mem::transmute::<[{lane_ty}; {len}], {}>([{}])",
+                    self.rust_arg_type(val)?,
+                    lanes.join(", ")
+                )
+            }
             Float(FloatVal::c_double(val)) => {
                 if val.fract() == 0.0 {
                     format!("{val}.0")
@@ -510,6 +1591,32 @@ impl RustcAbiImpl {
                     format!("{val}")
                 }
             }
+            Float(float_val) => {
+                let double_val = self.rust_val(&Float(FloatVal::c_double(float_val.to_f64())))?;
+                if let Some(stand_in) = self.rust_float_stand_in(float_val)? {
+                    let bytes = stand_in
+                        .bytes
+                        .iter()
+                        .map(|byte| format!("{byte:#04X}"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let padding = stand_in.size - stand_in.bytes.len();
+                    if padding != 0 {
+                        format!("{}([{bytes}], [0; {padding}])", stand_in.name)
+                    } else {
+                        format!("{}([{bytes}])", stand_in.name)
+                    }
+                } else {
+                    // Convert from a double so the rounding is the same everywhere
+                    format!("({double_val}f64 as {})", self.rust_arg_type(val)?)
+                }
+            }
+            Complex(re, im) => format!(
+                "{} {{ re: {}, im: {} }}",
+                self.rust_arg_type(val)?,
+                self.rust_val(&Float(re.clone()))?,
+                self.rust_val(&Float(im.clone()))?
+            ),
             Int(int_val) => match int_val {
                 c__int128(val) => {
                     if STRUCT_128 {
@@ -533,6 +1640,40 @@ impl RustcAbiImpl {
                 c_uint32_t(val) => format!("{val:#X}"),
                 c_uint16_t(val) => format!("{val:#X}"),
                 c_uint8_t(val) => format!("{val:#X}"),
+                // Casting (rather than suffixing) matches C's truncation on
+                // platforms where these are narrower than their value.
+                c_char(val) => format!("{val}i8 as core::ffi::c_char"),
+                c_schar(val) => format!("{val}i8 as core::ffi::c_schar"),
+                c_uchar(val) => format!("{val:#X}u8 as core::ffi::c_uchar"),
+                c_int(val) => format!("{val}i32 as core::ffi::c_int"),
+                c_uint(val) => format!("{val:#X}u32 as core::ffi::c_uint"),
+                c_long(val) => format!("{val}i64 as core::ffi::c_long"),
+                c_ulong(val) => format!("{val:#X}u64 as core::ffi::c_ulong"),
+                c_size_t(val) => format!("{val:#X}u64 as usize"),
+                c_ptrdiff_t(val) => format!("{val}i64 as isize"),
+                c_intptr_t(val) => format!("{val}i64 as isize"),
+                c_uintptr_t(val) => format!("{val:#X}u64 as usize"),
+                c_BitInt {
+                    bits,
+                    signed,
+                    value,
+                } => {
+                    if let Some((size, _)) = self.rust_bit_int_stand_in(*bits)? {
+                        let bytes = bit_int_bytes(*bits, *signed, value, size)
+                            .iter()
+                            .map(|byte| format!("{byte:#04X}"))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        format!("BitInt{bits}([{bytes}])")
+                    } else {
+                        let bytes = bit_int_bytes(*bits, false, value, 16);
+                        let val = u128::from_le_bytes(bytes.try_into().unwrap());
+                        format!(
+                            "{val:#X}u128 as {}",
+                            self.rust_arg_type(&Int(int_val.clone()))?
+                        )
+                    }
+                }
             },
         };
         Ok(out)
@@ -542,10 +1683,19 @@ impl RustcAbiImpl {
     fn rust_default_val(&self, val: &Val) -> Result<String, GenerateError> {
         use Val::*;
         let out = match val {
-            Ref(pointee) => self.rust_default_val(pointee)?,
+            Ref(pointee, _) => self.rust_default_val(pointee)?,
+            RustType(ty, val) => format!(
+                "core::mem::transmute::<{}, {ty}>({})",
+                self.rust_arg_type(val)?,
+                self.rust_default_val(val)?
+            ),
+            Transparent(name, val) => format!("{name}({})", self.rust_default_val(val)?),
             Ptr(_) => format!("0 as *mut ()"),
+            CStr(_) | WideCStr(_) => format!("core::ptr::null()"),
+            Atomic(_) => format!("{}::new(0)", self.rust_arg_type(val)?),
             Bool(_) => format!("false"),
-            Array(vals) => {
+            EmptyArray(_) | FlexArray(..) => format!("[]"),
+            Array(_, vals) => {
                 let mut output = String::new();
                 output.push_str(&format!("[",));
                 for elem in vals {
@@ -555,7 +1705,11 @@ impl RustcAbiImpl {
                 output.push_str("]");
                 output
             }
-            Struct(name, fields) => {
+            Struct(_, fields) if fields.iter().any(|field| matches!(field, BitField(..))) => {
+                format!("core::mem::zeroed()")
+            }
+            BitField(..) => format!("0"),
+            Struct(name, fields) | ReprStruct(name, _, fields) => {
                 let mut output = String::new();
                 output.push_str(&format!("{name} {{ "));
                 for (idx, field) in fields.iter().enumerate() {
@@ -565,7 +1719,51 @@ impl RustcAbiImpl {
                 output.push_str(" }");
                 output
             }
-            Float(..) => format!("0.0"),
+            Union(name, active, fields) => {
+                let field = fields.get(*active).ok_or_else(|| {
+                    GenerateError::RustUnsupported(format!("union {name} has no member {active}"))
+                })?;
+                format!(
+                    "{name} {{ {}: core::mem::ManuallyDrop::new({}) }}",
+                    FIELD_NAMES[*active],
+                    self.rust_default_val(field)?
+                )
+            }
+            Enum(name, _, variants, _) => format!("{name}::{}", variants[0].0),
+            DataEnum(name, _, variants, _) => {
+                let (variant, fields) = &variants[0];
+                let mut output = String::new();
+                output.push_str(&format!("{name}::{variant} {{ "));
+                for (idx, field) in fields.iter().enumerate() {
+                    let part = format!("{}: {},", FIELD_NAMES[idx], self.rust_default_val(field)?);
+                    output.push_str(&part);
+                }
+                output.push_str(" }");
+                output
+            }
+            FnPtr(..) => {
+                return Err(GenerateError::RustUnsupported(format!(
+                    "function pointers can't be returned"
+                )))
+            }
+            Vector(..) => format!("core::mem::zeroed()"),
+            Float(float_val) => {
+                if let Some(stand_in) = self.rust_float_stand_in(float_val)? {
+                    let padding = stand_in.size - stand_in.bytes.len();
+                    if padding != 0 {
+                        format!(
+                            "{}([0; {}], [0; {padding}])",
+                            stand_in.name,
+                            stand_in.bytes.len()
+                        )
+                    } else {
+                        format!("{}([0; {}])", stand_in.name, stand_in.size)
+                    }
+                } else {
+                    format!("0.0")
+                }
+            }
+            Complex(..) => format!("{} {{ re: 0.0, im: 0.0 }}", self.rust_arg_type(val)?),
             Int(IntVal::c__int128(..)) => {
                 if STRUCT_128 {
                     format!("FfiI128::new(0)")
@@ -580,6 +1778,13 @@ impl RustcAbiImpl {
                     format!("0")
                 }
             }
+            Int(IntVal::c_BitInt { bits, .. }) => {
+                if let Some((size, _)) = self.rust_bit_int_stand_in(*bits)? {
+                    format!("BitInt{bits}([0; {size}])")
+                } else {
+                    format!("0")
+                }
+            }
             Int(..) => format!("0"),
         };
         Ok(out)
@@ -613,31 +1818,194 @@ impl RustcAbiImpl {
         from: &str,
         is_var_root: bool,
     ) -> Result<Vec<String>, GenerateError> {
+        self.rust_place_paths(val, from, is_var_root, false)
+    }
+
+    /// rust_var_paths, but `from` may be (inside) a field of a packed struct.
+    ///
+    /// Taking a reference to a packed field isn't allowed (it may be unaligned),
+    /// so while `is_packed` the paths stay places, and the leaves are copied out
+    /// with read_unaligned. Anything that needs `&self` to be read (bitfields,
+    /// unions) gets copied out whole first.
+    fn rust_place_paths(
+        &self,
+        val: &Val,
+        from: &str,
+        is_var_root: bool,
+        is_packed: bool,
+    ) -> Result<Vec<String>, GenerateError> {
+        let unaligned = |from: &str| format!("(core::ptr::addr_of!({from}).read_unaligned())");
         let paths = match val {
-            Val::Int(_) | Val::Float(_) | Val::Bool(_) | Val::Ptr(_) => {
-                vec![format!("{from}")]
+            Val::RustType(_, val) => self.rust_place_paths(val, from, is_var_root, is_packed)?,
+            Val::Transparent(_, val) => {
+                let base = format!("{from}.0");
+                self.rust_place_paths(val, &base, false, is_packed)?
             }
-            Val::Struct(_name, fields) => {
+            Val::CStr(_) | Val::WideCStr(_) => {
+                // The contents (up to and including the NUL), not the pointer
+                if is_packed {
+                    vec![format!("str_with_nul({})[..]", unaligned(from))]
+                } else {
+                    vec![format!("str_with_nul({from})[..]")]
+                }
+            }
+            Val::Int(IntVal::c_BitInt { bits, .. })
+                if self.rust_bit_int_stand_in(*bits)?.is_some() =>
+            {
+                // Only the bytes that hold the value, the rest is padding
+                let len = bits.div_ceil(8);
+                if is_packed {
+                    vec![format!("{}[..{len}]", unaligned(&format!("{from}.0")))]
+                } else {
+                    vec![format!("{from}.0[..{len}]")]
+                }
+            }
+            Val::Float(float_val) if self.rust_float_stand_in(float_val)?.is_some() => {
+                // Just the bytes of the value, not the padding
+                let path = format!("{from}.0");
+                if is_packed {
+                    vec![unaligned(&path)]
+                } else {
+                    vec![path]
+                }
+            }
+            Val::Int(_)
+            | Val::Float(_)
+            | Val::Bool(_)
+            | Val::Ptr(_)
+            | Val::Vector(_)
+            | Val::Enum(..)
+            | Val::Atomic(_) => {
+                if is_packed {
+                    vec![unaligned(from)]
+                } else {
+                    vec![format!("{from}")]
+                }
+            }
+            Val::Struct(_name, fields)
+                if is_packed
+                    && fields
+                        .iter()
+                        .any(|field| matches!(field, Val::BitField(..))) =>
+            {
+                self.rust_place_paths(val, &unaligned(from), false, false)?
+            }
+            Val::Union(..) if is_packed => {
+                self.rust_place_paths(val, &unaligned(from), false, false)?
+            }
+            Val::Struct(_name, fields) | Val::ReprStruct(_name, _, fields) => {
+                let layout = if fields
+                    .iter()
+                    .any(|field| matches!(field, Val::BitField(..)))
+                {
+                    Some(
+                        bitfield_layout(fields, &self.target)
+                            .map_err(GenerateError::RustUnsupported)?,
+                    )
+                } else {
+                    None
+                };
+                let is_packed =
+                    is_packed || matches!(val, Val::ReprStruct(_, StructRepr::Packed(_), _));
                 let mut paths = vec![];
                 for (idx, field) in fields.iter().enumerate() {
                     let base = format!("{from}.{}", FIELD_NAMES[idx]);
-                    paths.extend(self.rust_var_paths(field, &base, false)?);
+                    paths.extend(self.rust_place_paths(field, &base, false, is_packed)?);
+
+                    // After the last field of a run of bitfields, the raw bytes
+                    // of the run's storage are reported as a pseudo-field.
+                    let runs = layout.iter().flat_map(|layout| &layout.runs);
+                    for (run, _) in runs.filter(|(run, _)| run.end == idx + 1) {
+                        paths.push(format!("{from}.bitfields{}", run.start));
+                    }
+                }
+                paths
+            }
+            Val::Complex(..) => {
+                let parts = [format!("{from}.re"), format!("{from}.im")];
+                if is_packed {
+                    parts.iter().map(|part| unaligned(part)).collect()
+                } else {
+                    parts.to_vec()
+                }
+            }
+            Val::BitField(0, _) => vec![],
+            // Read out by the accessor (see rust_bitfield_struct_decl)
+            Val::BitField(..) => vec![format!("{from}()")],
+            Val::Union(name, active, fields) => {
+                // The size of the union is reported as a pseudo-field, followed
+                // by the fields of the active member.
+                let field = fields.get(*active).ok_or_else(|| {
+                    GenerateError::RustUnsupported(format!("union {name} has no member {active}"))
+                })?;
+                let mut paths = vec![format!("(core::mem::size_of_val(&{from}) as u32)")];
+                let base = format!("{from}.{}", FIELD_NAMES[*active]);
+                paths.extend(self.rust_place_paths(field, &base, false, false)?);
+                paths
+            }
+            Val::DataEnum(name, repr, variants, active) => {
+                // The size of the enum and its tag are reported as pseudo-fields,
+                // followed by the fields of the active variant.
+                let (variant, fields) = variants.get(*active).ok_or_else(|| {
+                    GenerateError::RustUnsupported(format!("enum {name} has no variant {active}"))
+                })?;
+                // Matching on the enum would be UB if C disagrees about the tag,
+                // so read it as the tagged union it's laid out as instead.
+                let (tag, base) = if *repr == EnumRepr::C {
+                    let repr = format!("(*(core::ptr::addr_of!({from}) as *const {name}_Repr))");
+                    // (every member of a repr(C) union is at its start)
+                    let payload = format!(
+                        "(*(core::ptr::addr_of!({repr}.payload) as *const {name}_{variant}))"
+                    );
+                    (format!("{repr}.tag"), payload)
+                } else {
+                    let repr =
+                        format!("(*(core::ptr::addr_of!({from}) as *const {name}_{variant}))");
+                    (format!("{repr}.tag"), repr)
+                };
+                let mut paths = vec![format!("(core::mem::size_of_val(&{from}) as u32)"), tag];
+                for (idx, field) in fields.iter().enumerate() {
+                    let base = format!("{base}.{}", FIELD_NAMES[idx]);
+                    paths.extend(self.rust_place_paths(field, &base, false, false)?);
                 }
                 paths
             }
-            Val::Ref(pointee) => {
+            Val::Ref(pointee, flavor) => {
+                // Whatever the pointer points to is aligned
                 if is_var_root {
-                    self.rust_var_paths(pointee, from, false)?
+                    self.rust_place_paths(pointee, from, false, false)?
                 } else {
-                    let base = format!("(*{from})");
-                    self.rust_var_paths(pointee, &base, false)?
+                    let base = rust_ref_deref(*flavor, from);
+                    self.rust_place_paths(pointee, &base, false, false)?
                 }
             }
-            Val::Array(vals) => {
+            Val::Array(_, vals) => {
                 let mut paths = vec![];
                 for (i, elem) in vals.iter().enumerate() {
                     let base = format!("{from}[{i}]");
-                    paths.extend(self.rust_var_paths(elem, &base, false)?);
+                    paths.extend(self.rust_place_paths(elem, &base, false, is_packed)?);
+                }
+                paths
+            }
+            Val::EmptyArray(_) => vec![],
+            Val::FlexArray(elem_ty, vals) => {
+                // The elements are past the end of the (zero-length) array
+                let elem_ty = self.rust_nested_type(elem_ty)?;
+                let mut paths = vec![];
+                for (i, elem) in vals.iter().enumerate() {
+                    let base =
+                        format!("(*core::ptr::addr_of!({from}).cast::<{elem_ty}>().add({i}))");
+                    paths.extend(self.rust_place_paths(elem, &base, false, is_packed)?);
+                }
+                paths
+            }
+            Val::FnPtr(_name, _, args) => {
+                // The pointer itself isn't interesting, the args it gets
+                // called with are (see write_rust_fn_ptr_args).
+                let mut paths = vec![];
+                for (idx, arg) in args.iter().enumerate() {
+                    let base = format!("{from}_{}", ARG_NAMES[idx]);
+                    paths.extend(self.rust_place_paths(arg, &base, true, false)?);
                 }
                 paths
             }
@@ -646,3 +2014,45 @@ impl RustcAbiImpl {
         Ok(paths)
     }
 }
+
+/// The number of elements in this struct's flexible array member, if it has one.
+fn flex_array_len(val: &Val) -> Option<usize> {
+    match val {
+        Val::Struct(_, fields) | Val::ReprStruct(_, _, fields) => match fields.last() {
+            Some(Val::FlexArray(_, vals)) => Some(vals.len()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Turn `ptr` (a `*mut T`) into this flavor of pointer to T.
+fn rust_ref_from_ptr(flavor: RefFlavor, ptr: &str) -> String {
+    match flavor {
+        RefFlavor::Default | RefFlavor::Ref => format!("&*{ptr}"),
+        RefFlavor::RefMut => format!("&mut *{ptr}"),
+        RefFlavor::OptionRef => format!("Some(&*{ptr})"),
+        RefFlavor::OptionRefMut => format!("Some(&mut *{ptr})"),
+        RefFlavor::NonNull => format!("core::ptr::NonNull::new_unchecked({ptr})"),
+        // The callee forgets these instead of freeing our stack
+        RefFlavor::Box => format!("Box::from_raw({ptr})"),
+        RefFlavor::OptionBox => format!("Some(Box::from_raw({ptr}))"),
+        RefFlavor::ConstPtr => format!("{ptr} as *const _"),
+        RefFlavor::MutPtr => format!("{ptr}"),
+    }
+}
+
+/// The place that this flavor of pointer named `ptr` points to.
+fn rust_ref_deref(flavor: RefFlavor, ptr: &str) -> String {
+    match flavor {
+        RefFlavor::Default
+        | RefFlavor::Ref
+        | RefFlavor::RefMut
+        | RefFlavor::Box
+        | RefFlavor::ConstPtr
+        | RefFlavor::MutPtr => format!("(*{ptr})"),
+        RefFlavor::OptionRef => format!("(*{ptr}.unwrap())"),
+        RefFlavor::OptionRefMut | RefFlavor::OptionBox => format!("(**{ptr}.as_ref().unwrap())"),
+        RefFlavor::NonNull => format!("(*{ptr}.as_ptr())"),
+    }
+}
@@ -1,8 +1,9 @@
 mod abis;
+mod report;
 
 use abis::*;
-use clap::{AppSettings, Arg};
-use std::collections::HashMap;
+use clap::{AppSettings, Arg, ErrorKind};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::error::Error;
 use std::fs::File;
@@ -10,6 +11,7 @@ use std::io::BufReader;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{Duration, Instant};
 /*
 use log::error;
 use simplelog::{
@@ -34,12 +36,18 @@ pub enum BuildError {
         width=.2.position.col.saturating_sub(1),
 )]
     ParseError(String, String, ron::error::Error),
+    #[error("type error {0}\n{1}")]
+    TypeError(String, String),
     #[error("rust compile error \n{} \n{}", 
         std::str::from_utf8(&.0.stdout).unwrap(),
         std::str::from_utf8(&.0.stderr).unwrap())]
     RustCompile(std::process::Output),
     #[error("c compile errror\n{0}")]
     CCompile(#[from] cc::Error),
+    #[error("c compile error \n{} \n{}",
+        std::str::from_utf8(&.0.stdout).unwrap(),
+        std::str::from_utf8(&.0.stderr).unwrap())]
+    CTool(std::process::Output),
     #[error("test loading error (dynamic linking failed)\n{0}")]
     LoadError(#[from] libloading::Error),
     #[error("test uses features unsupported by this backend\n{0}")]
@@ -56,6 +64,12 @@ pub enum BuildError {
     HandwrittenMixing,
     #[error("No handwritten source for this pairing (skipping)")]
     NoHandwrittenSource,
+    #[error("test harness crashed after all tests finished\n{0}")]
+    HarnessCrashed(String),
+    #[error("test harness timed out after {0:?} after all tests finished")]
+    HarnessTimedOut(Duration),
+    #[error("test harness reported garbage\n{0}")]
+    HarnessProtocol(String),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -74,10 +88,27 @@ pub enum TestFailure {
         OUTPUT_NAME
     )]
     OutputFieldCountMismatch(usize, usize, Vec<Vec<u8>>, Vec<Vec<u8>>),
+    #[error("test {0} {} field {2} is an enum with mismatched sizes \ncaller: {3} bytes \ncallee: {4} bytes", ARG_NAMES[*.1])]
+    InputEnumSizeMismatch(usize, usize, usize, usize, usize),
+    #[error(
+        "test {0} {} field {2} is an enum with mismatched sizes \ncaller: {3} bytes \ncallee: {4} bytes",
+        OUTPUT_NAME
+    )]
+    OutputEnumSizeMismatch(usize, usize, usize, usize, usize),
     #[error("test {0} input count mismatch \ncaller: {1:#02X?} \ncallee: {2:#02X?}")]
     InputCountMismatch(usize, Vec<Vec<Vec<u8>>>, Vec<Vec<Vec<u8>>>),
     #[error("test {0} output count mismatch \ncaller: {1:#02X?} \ncallee: {2:#02X?}")]
     OutputCountMismatch(usize, Vec<Vec<Vec<u8>>>, Vec<Vec<Vec<u8>>>),
+    #[error("test {0} crashed the test harness \n{1}")]
+    Crashed(usize, String),
+    #[error("test {0} timed out after {1:?}")]
+    TimedOut(usize, Duration),
+    #[error(
+        "test {0} aborted the test harness (probably an unwind that couldn't be caught) \n{1}"
+    )]
+    Aborted(usize, String),
+    #[error("test {0} didn't run because an earlier test crashed or hung")]
+    NotRun(usize),
 }
 
 #[derive(Debug)]
@@ -86,6 +117,16 @@ pub struct TestReport {
     results: Vec<Result<(), TestFailure>>,
 }
 
+/// The outcome of one test under one convention for one "X calls Y" pair:
+/// (test name, convention, caller name, callee name, report).
+pub type TestOutcome = (
+    String,
+    CallingConvention,
+    &'static str,
+    &'static str,
+    Result<TestReport, BuildError>,
+);
+
 #[derive(Debug, Clone)]
 pub struct Config {
     procgen_tests: bool,
@@ -93,6 +134,22 @@ pub struct Config {
     run_impls: Vec<String>,
     run_pairs: Vec<(String, String)>,
     run_tests: Vec<String>,
+    target: Target,
+    target_runner: Vec<String>,
+    runner: Runner,
+    timeout: Duration,
+    report_json: Option<PathBuf>,
+    report_junit: Option<PathBuf>,
+}
+
+/// How the test harness gets run.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Runner {
+    /// Load the harness as a dylib and run it inside abi-checker.
+    InProcess,
+    /// Build the harness as an executable and run it in a child process,
+    /// so that a crash or hang only takes out that one test.
+    OutOfProcess,
 }
 
 fn make_app() -> Config {
@@ -110,7 +167,7 @@ fn make_app() -> Config {
         (ABI_IMPL_CC, ABI_IMPL_CC),    // C calls C
     ];
 
-    let app = clap::Command::new("abi-checker")
+    let mut app = clap::Command::new("abi-checker")
         .version(clap::crate_version!())
         .about("Compares the FFI ABIs of different langs/compilers by generating and running them.")
         .next_line_help(true)
@@ -130,6 +187,11 @@ fn make_app() -> Config {
                     "fastcall",
                     "stdcall",
                     "vectorcall",
+                    "win64",
+                    "sysv64",
+                    "thiscall",
+                    "c-unwind",
+                    "system-unwind",
                     "handwritten",
                 ])
                 .multiple_values(true)
@@ -157,9 +219,51 @@ fn make_app() -> Config {
                 .multiple_values(true)
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("target")
+                .long("target")
+                .long_help("Build and run the tests for this target triple (defaults to the host)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("target-runner")
+                .long("target-runner")
+                .long_help("The command to run the --target's executables with (defaults to qemu-<arch> if the host can't run them)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("runner")
+                .long("runner")
+                .long_help(
+                    "How to run the tests (out-of-process survives tests that crash or hang)",
+                )
+                .possible_values(&["in-process", "out-of-process"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("timeout")
+                .long("timeout")
+                .long_help("How many seconds an out-of-process test may run before it's killed")
+                .default_value("30")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("report-json")
+                .long("report-json")
+                .long_help(
+                    "Also write the full results (including mismatched bytes) to this file as JSON",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("report-junit")
+                .long("report-junit")
+                .long_help("Also write the results to this file as JUnit XML")
+                .takes_value(true),
+        )
         .after_help("");
 
-    let matches = app.get_matches();
+    let matches = app.get_matches_mut();
     let procgen_tests = matches.is_present("procgen-tests");
 
     let mut run_conventions: Vec<_> = matches
@@ -205,12 +309,50 @@ fn make_app() -> Config {
         .map(String::from)
         .collect();
 
+    let target = matches
+        .value_of("target")
+        .map(Target::new)
+        .unwrap_or_else(Target::host);
+
+    let target_runner = matches
+        .value_of("target-runner")
+        .map(String::from)
+        .or_else(|| target.qemu_runner())
+        .map(|runner| runner.split_whitespace().map(String::from).collect())
+        .unwrap_or_default();
+
+    // We can't load another target's dylib into our own process, so only the
+    // host can run in-process (which is the default for it).
+    let runner = match matches.value_of("runner") {
+        Some("in-process") if !target.is_host() => app
+            .error(
+                ErrorKind::ArgumentConflict,
+                "only the host target can use --runner in-process",
+            )
+            .exit(),
+        Some("in-process") => Runner::InProcess,
+        Some(_) => Runner::OutOfProcess,
+        None if target.is_host() => Runner::InProcess,
+        None => Runner::OutOfProcess,
+    };
+
+    let timeout = Duration::from_secs(matches.value_of_t_or_exit("timeout"));
+
+    let report_json = matches.value_of("report-json").map(PathBuf::from);
+    let report_junit = matches.value_of("report-junit").map(PathBuf::from);
+
     Config {
         procgen_tests,
         run_conventions,
         run_impls,
         run_tests,
         run_pairs,
+        target,
+        target_runner,
+        runner,
+        timeout,
+        report_json,
+        report_junit,
     }
 }
 
@@ -227,29 +369,19 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Set up env vars for CC
     env::set_var("OUT_DIR", &out_dir);
     env::set_var("HOST", built_info::HOST);
-    env::set_var("TARGET", built_info::TARGET);
+    env::set_var("TARGET", &cfg.target.triple);
     env::set_var("OPT_LEVEL", "0");
 
     let mut abi_impls: HashMap<&str, Box<dyn AbiImpl>> = HashMap::new();
     abi_impls.insert(ABI_IMPL_RUSTC, Box::new(abis::RustcAbiImpl::new(&cfg)));
-    abi_impls.insert(
-        ABI_IMPL_CC,
-        Box::new(abis::CcAbiImpl::new(&cfg, ABI_IMPL_CC)),
-    );
-    abi_impls.insert(
-        ABI_IMPL_GCC,
-        Box::new(abis::CcAbiImpl::new(&cfg, ABI_IMPL_GCC)),
-    );
-    abi_impls.insert(
-        ABI_IMPL_CLANG,
-        Box::new(abis::CcAbiImpl::new(&cfg, ABI_IMPL_CLANG)),
-    );
-    abi_impls.insert(
-        ABI_IMPL_MSVC,
-        Box::new(abis::CcAbiImpl::new(&cfg, ABI_IMPL_MSVC)),
-    );
+    let mut c_compilers = BTreeMap::new();
+    for mode in [ABI_IMPL_CC, ABI_IMPL_GCC, ABI_IMPL_CLANG, ABI_IMPL_MSVC] {
+        let abi_impl = abis::CcAbiImpl::new(&cfg, mode);
+        c_compilers.insert(mode, abi_impl.compiler());
+        abi_impls.insert(mode, Box::new(abi_impl));
+    }
 
-    let mut reports = Vec::new();
+    let mut reports = Vec::<TestOutcome>::new();
     let mut skips = 0;
 
     // Grab all the tests
@@ -316,7 +448,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                     continue;
                 }
 
-                let result = do_test(&test, convention, caller, callee, &out_dir);
+                let result = do_test(&test, convention, caller, callee, &out_dir, &cfg);
 
                 if let Err(BuildError::NoHandwrittenSource) = &result {
                     eprintln!(
@@ -324,6 +456,10 @@ fn main() -> Result<(), Box<dyn Error>> {
                     );
                     skips += 1;
                     continue;
+                } else if let Err(BuildError::Unsupported(e)) = &result {
+                    eprintln!("skipping {full_test_name}: {e}");
+                    skips += 1;
+                    continue;
                 } else if let Err(e) = &result {
                     eprintln!("test failed: {}", e);
                 }
@@ -338,6 +474,17 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    // Write out the machine-readable reports before the printout eats the results
+    if cfg.report_json.is_some() || cfg.report_junit.is_some() {
+        let run_report = report::RunReport::new(&reports, &cfg.target, &c_compilers);
+        if let Some(path) = &cfg.report_json {
+            std::fs::write(path, run_report.to_json())?;
+        }
+        if let Some(path) = &cfg.report_junit {
+            std::fs::write(path, run_report.to_junit())?;
+        }
+    }
+
     println!();
     println!("Final Results:");
     // Do a cleaned up printout now
@@ -388,8 +535,14 @@ fn main() -> Result<(), Box<dyn Error>> {
                 let max_name_len = names.iter().fold(0, |max, name| max.max(name.len()));
                 for (subtest_name, result) in names.iter().zip(report.results.iter()) {
                     print!("  {:width$} ", subtest_name, width = max_name_len);
-                    if let Err(_e) = result {
-                        println!("failed!");
+                    if let Err(e) = result {
+                        match e {
+                            TestFailure::Crashed(..) => println!("crashed!"),
+                            TestFailure::TimedOut(..) => println!("timed out!"),
+                            TestFailure::Aborted(..) => println!("aborted!"),
+                            TestFailure::NotRun(..) => println!("not run"),
+                            _ => println!("failed!"),
+                        }
                         // A bit too noisy?
                         // println!("{}", e);
                         fails += 1;
@@ -415,6 +568,7 @@ fn do_test(
     caller: &dyn AbiImpl,
     callee: &dyn AbiImpl,
     _out_dir: &Path,
+    cfg: &Config,
 ) -> Result<TestReport, BuildError> {
     let test_name = &test.name;
     let convention_name = convention.name();
@@ -458,14 +612,49 @@ fn do_test(
     }
     eprintln!("compiling  {full_test_name}");
     // Compile the tests (and let them change the lib name).
-    let caller_lib = caller.compile_caller(&caller_src, &caller_lib)?;
-    let callee_lib = callee.compile_callee(&callee_src, &callee_lib)?;
+    let caller_lib = caller.compile_caller(test, &caller_src, &caller_lib)?;
+    let callee_lib = callee.compile_callee(test, &callee_src, &callee_lib)?;
+
+    // Unwinding tests are likely to take down the whole harness if something
+    // goes wrong, so always keep those (and tests known to crash) at arm's length.
+    let reason = if test.funcs.iter().any(|func| func.callee_unwinds) {
+        Some("its callee unwinds")
+    } else if test.out_of_process {
+        Some("it's known to crash")
+    } else {
+        None
+    };
+    let runner = match reason {
+        Some(reason) => {
+            if !matches!(cfg.runner, Runner::OutOfProcess) {
+                eprintln!("running    {full_test_name} out-of-process, because {reason}");
+            }
+            Runner::OutOfProcess
+        }
+        None => cfg.runner,
+    };
 
-    // Compile the harness dylib and link in the tests.
-    let dylib = build_harness(test, caller_name, &caller_lib, callee_name, &callee_lib)?;
+    // Compile the harness and link in the tests.
+    let harness = build_harness(
+        test,
+        caller_name,
+        &caller_lib,
+        callee_name,
+        &callee_lib,
+        &cfg.target,
+        runner,
+    )?;
 
     // Load and run the test
-    run_dynamic_test(test, convention_name, caller_name, callee_name, &dylib)
+    run_dynamic_test(
+        test,
+        convention_name,
+        caller_name,
+        callee_name,
+        &harness,
+        runner,
+        cfg,
+    )
 }
 
 /// Read a test .ron file
@@ -474,9 +663,10 @@ fn read_test_manifest(test_file: &Path) -> Result<Test, BuildError> {
     let mut reader = BufReader::new(file);
     let mut input = String::new();
     reader.read_to_string(&mut input)?;
-    let test: Test = ron::from_str(&input)
+    let test: Test<RawVal> = ron::from_str(&input)
         .map_err(|e| BuildError::ParseError(test_file.to_string_lossy().into_owned(), input, e))?;
-    Ok(test)
+    test.resolve_types()
+        .map_err(|e| BuildError::TypeError(test_file.to_string_lossy().into_owned(), e))
 }
 
 /// Compile and link the test harness with the two sides of the FFI boundary.
@@ -486,12 +676,43 @@ fn build_harness(
     caller_lib: &str,
     callee_name: &str,
     callee_lib: &str,
+    target: &Target,
+    runner: Runner,
 ) -> Result<String, BuildError> {
     let test_name = &test.name;
     let src = PathBuf::from("harness/harness.rs");
-    let output = format!("target/temp/{test_name}_{caller_name}_calls_{callee_name}_harness.dll");
+    let mut cmd = Command::new("rustc");
+    if !target.is_host() {
+        cmd.arg("--target").arg(&target.triple);
+    }
+    if !target.runs_on_host() {
+        // rustc has no idea where the cross toolchain is, but cc does.
+        // Linking statically also means qemu doesn't need to be told
+        // where to find the target's libc.
+        let linker = cc::Build::new().try_get_compiler()?;
+        cmd.arg("-C")
+            .arg(format!("linker={}", linker.path().display()))
+            .arg("-C")
+            .arg("target-feature=+crt-static");
+    }
+    let output = match runner {
+        Runner::InProcess => {
+            cmd.arg("--crate-type").arg("cdylib");
+            format!("target/temp/{test_name}_{caller_name}_calls_{callee_name}_harness.dll")
+        }
+        Runner::OutOfProcess => {
+            cmd.arg("--crate-type")
+                .arg("bin")
+                .arg("--cfg")
+                .arg("harness_exe");
+            format!(
+                "target/temp/{test_name}_{caller_name}_calls_{callee_name}_harness{}",
+                env::consts::EXE_SUFFIX
+            )
+        }
+    };
 
-    let out = Command::new("rustc")
+    let out = cmd
         .arg("-v")
         .arg("-L")
         .arg("target/temp/")
@@ -499,8 +720,6 @@ fn build_harness(
         .arg(&caller_lib)
         .arg("-l")
         .arg(&callee_lib)
-        .arg("--crate-type")
-        .arg("cdylib")
         // .arg("--out-dir")
         // .arg("target/temp/")
         .arg("-o")
@@ -515,314 +734,539 @@ fn build_harness(
     }
 }
 
-/// Run the test!
-fn run_dynamic_test(
-    test: &Test,
-    convention_name: &str,
-    caller_name: &str,
-    callee_name: &str,
-    dylib: &str,
-) -> Result<TestReport, BuildError> {
-    // See the README for a high-level description of this design.
-
-    ////////////////////////////////////////////////////////////////////
-    //////////////////// DEFINING THE TEST HARNESS /////////////////////
-    ////////////////////////////////////////////////////////////////////
-
-    // The signatures of the interface from our perspective.
-    // From the test's perspective the WriteBuffers are totally opaque.
-    type WriteCallback = unsafe extern "C" fn(&mut WriteBuffer, *const u8, u32) -> ();
-    type FinishedValCallback = unsafe extern "C" fn(&mut WriteBuffer) -> ();
-    type FinishedFuncCallback = unsafe extern "C" fn(&mut WriteBuffer, &mut WriteBuffer) -> ();
-    type TestInit = unsafe extern "C" fn(
-        WriteCallback,
-        FinishedValCallback,
-        FinishedFuncCallback,
-        &mut WriteBuffer,
-        &mut WriteBuffer,
-        &mut WriteBuffer,
-        &mut WriteBuffer,
-    ) -> ();
-
-    /// Tests write back the raw bytes of their values to a WriteBuffer.
-    ///
-    /// This hierarchical design is confusing as hell, but represents the
-    /// nested levels of abstraction we are concerned with:
-    ///
-    /// subtests (functions) => values (args/returns) => subfields => bytes.
-    ///
-    /// Having this much hierarchy means that we can specifically say
-    /// "ah yeah, on test 3 the two sides disagreed on arg2.field1.field2"
-    /// and also reduces the chance of failures in one test "cascading"
-    /// into the subsequent ones.
-    struct WriteBuffer {
-        funcs: Vec<Vec<Vec<Vec<u8>>>>,
-    }
+// The signatures of the interface from our perspective.
+// From the test's perspective the WriteBuffers are totally opaque.
+type WriteCallback = unsafe extern "C" fn(&mut WriteBuffer, *const u8, u32) -> ();
+type FinishedValCallback = unsafe extern "C" fn(&mut WriteBuffer) -> ();
+type FinishedFuncCallback = unsafe extern "C" fn(&mut WriteBuffer, &mut WriteBuffer) -> ();
+type TestInit = unsafe extern "C" fn(
+    WriteCallback,
+    FinishedValCallback,
+    FinishedFuncCallback,
+    &mut WriteBuffer,
+    &mut WriteBuffer,
+    &mut WriteBuffer,
+    &mut WriteBuffer,
+) -> ();
+
+/// Tests write back the raw bytes of their values to a WriteBuffer.
+///
+/// This hierarchical design is confusing as hell, but represents the
+/// nested levels of abstraction we are concerned with:
+///
+/// subtests (functions) => values (args/returns) => subfields => bytes.
+///
+/// Having this much hierarchy means that we can specifically say
+/// "ah yeah, on test 3 the two sides disagreed on arg2.field1.field2"
+/// and also reduces the chance of failures in one test "cascading"
+/// into the subsequent ones.
+struct WriteBuffer {
+    funcs: Vec<Vec<Vec<Vec<u8>>>>,
+}
 
-    impl WriteBuffer {
-        fn new() -> Self {
-            // Preload the hierarchy for the first test.
-            WriteBuffer {
-                funcs: vec![vec![vec![]]],
-            }
-        }
-        fn finish_tests(&mut self) {
-            // Remove the pending test
-            self.funcs.pop();
+impl WriteBuffer {
+    fn new() -> Self {
+        // Preload the hierarchy for the first test.
+        WriteBuffer {
+            funcs: vec![vec![vec![]]],
         }
     }
-
-    unsafe extern "C" fn write_field(output: &mut WriteBuffer, input: *const u8, size: u32) {
+    fn finish_tests(&mut self) {
+        // Remove the pending test
+        self.funcs.pop();
+    }
+    fn write_field(&mut self, data: &[u8]) {
         // Push the bytes of an individual field
-        let data = std::slice::from_raw_parts(input, size as usize);
-        output
-            .funcs
+        self.funcs
             .last_mut() // values
             .unwrap()
             .last_mut() // fields
             .unwrap()
             .push(data.to_vec());
     }
-    unsafe extern "C" fn finished_val(output: &mut WriteBuffer) {
+    fn finished_val(&mut self) {
         // This value is finished, push a new entry
-        output
-            .funcs
+        self.funcs
             .last_mut() // values
             .unwrap()
             .push(vec![]);
     }
-    unsafe extern "C" fn finished_func(output1: &mut WriteBuffer, output2: &mut WriteBuffer) {
+    fn finished_func(&mut self) {
         // Remove the pending value
-        output1
-            .funcs
-            .last_mut() // values
-            .unwrap()
-            .pop()
-            .unwrap();
-        output2
-            .funcs
+        self.funcs
             .last_mut() // values
             .unwrap()
             .pop()
             .unwrap();
 
         // Push a new pending function
-        output1.funcs.push(vec![vec![]]);
-        output2.funcs.push(vec![vec![]]);
+        self.funcs.push(vec![vec![]]);
     }
+}
+
+unsafe extern "C" fn write_field(output: &mut WriteBuffer, input: *const u8, size: u32) {
+    let data = std::slice::from_raw_parts(input, size as usize);
+    output.write_field(data);
+}
+unsafe extern "C" fn finished_val(output: &mut WriteBuffer) {
+    output.finished_val();
+}
+unsafe extern "C" fn finished_func(output1: &mut WriteBuffer, output2: &mut WriteBuffer) {
+    output1.finished_func();
+    output2.finished_func();
+}
+
+/// Everything a test harness reported, before it's been checked.
+struct TestRun {
+    caller_inputs: WriteBuffer,
+    caller_outputs: WriteBuffer,
+    callee_inputs: WriteBuffer,
+    callee_outputs: WriteBuffer,
+    /// Set if the harness died before it finished running.
+    interrupted: Option<Interruption>,
+}
+
+/// Why an out-of-process test harness died early.
+enum Interruption {
+    Crashed(String),
+    TimedOut(Duration),
+    Aborted(String),
+}
+
+/// Run the test!
+fn run_dynamic_test(
+    test: &Test,
+    convention_name: &str,
+    caller_name: &str,
+    callee_name: &str,
+    harness: &str,
+    runner: Runner,
+    cfg: &Config,
+) -> Result<TestReport, BuildError> {
+    // See the README for a high-level description of this design.
 
     ////////////////////////////////////////////////////////////////////
     //////////////////// THE ACTUAL TEST EXECUTION /////////////////////
     ////////////////////////////////////////////////////////////////////
 
-    unsafe {
-        let full_test_name = full_test_name(&test.name, convention_name, caller_name, callee_name);
-        // Initialize all the buffers the tests will write to
-        let mut caller_inputs = WriteBuffer::new();
-        let mut caller_outputs = WriteBuffer::new();
-        let mut callee_inputs = WriteBuffer::new();
-        let mut callee_outputs = WriteBuffer::new();
-
-        // Load the dylib of the test, and get its test_start symbol
-        let lib = libloading::Library::new(dylib)?;
-        let do_test: libloading::Symbol<TestInit> = lib.get(b"test_start")?;
-        eprintln!("running    {full_test_name}");
-
-        // Actually run the test!
-        do_test(
-            write_field,
-            finished_val,
-            finished_func,
-            &mut caller_inputs,
-            &mut caller_outputs,
-            &mut callee_inputs,
-            &mut callee_outputs,
-        );
+    let full_test_name = full_test_name(&test.name, convention_name, caller_name, callee_name);
+    eprintln!("running    {full_test_name}");
+
+    // Actually run the test!
+    let TestRun {
+        mut caller_inputs,
+        mut caller_outputs,
+        mut callee_inputs,
+        mut callee_outputs,
+        interrupted,
+    } = match runner {
+        Runner::InProcess => run_in_process(harness)?,
+        Runner::OutOfProcess => run_out_of_process(harness, &cfg.target_runner, cfg.timeout)?,
+    };
 
-        // Finalize the buffers (clear all the pending values).
-        caller_inputs.finish_tests();
-        caller_outputs.finish_tests();
-        callee_inputs.finish_tests();
-        callee_outputs.finish_tests();
-
-        // Now check the results
-
-        // As a basic sanity-check, make sure everything agrees on how
-        // many tests actually executed. If this fails, then something
-        // is very fundamentally broken and needs to be fixed.
-        let expected_test_count = test.funcs.len();
-        if caller_inputs.funcs.len() != expected_test_count
-            || caller_outputs.funcs.len() != expected_test_count
-            || callee_inputs.funcs.len() != expected_test_count
-            || callee_outputs.funcs.len() != expected_test_count
-        {
-            return Err(BuildError::TestCountMismatch(
-                expected_test_count,
-                caller_inputs.funcs.len(),
-                caller_outputs.funcs.len(),
-                callee_inputs.funcs.len(),
-                callee_outputs.funcs.len(),
-            ));
+    // Finalize the buffers (clear all the pending values).
+    caller_inputs.finish_tests();
+    caller_outputs.finish_tests();
+    callee_inputs.finish_tests();
+    callee_outputs.finish_tests();
+
+    let mut expected_test_count = test.funcs.len();
+    if let Some(interruption) = &interrupted {
+        // The caller is the last one to report on each test, so only the
+        // tests it finished have complete results. If that's all of them,
+        // there's no test to blame, so the whole thing is a write-off.
+        let completed = caller_inputs.funcs.len();
+        if completed >= expected_test_count {
+            return Err(match interruption {
+                Interruption::Crashed(status) | Interruption::Aborted(status) => {
+                    BuildError::HarnessCrashed(status.clone())
+                }
+                Interruption::TimedOut(timeout) => BuildError::HarnessTimedOut(*timeout),
+            });
         }
+        caller_outputs.funcs.truncate(completed);
+        callee_inputs.funcs.truncate(completed);
+        callee_outputs.funcs.truncate(completed);
+        expected_test_count = completed;
+    }
+
+    // Now check the results
+
+    // As a basic sanity-check, make sure everything agrees on how
+    // many tests actually executed. If this fails, then something
+    // is very fundamentally broken and needs to be fixed.
+    if caller_inputs.funcs.len() != expected_test_count
+        || caller_outputs.funcs.len() != expected_test_count
+        || callee_inputs.funcs.len() != expected_test_count
+        || callee_outputs.funcs.len() != expected_test_count
+    {
+        return Err(BuildError::TestCountMismatch(
+            expected_test_count,
+            caller_inputs.funcs.len(),
+            caller_outputs.funcs.len(),
+            callee_inputs.funcs.len(),
+            callee_outputs.funcs.len(),
+        ));
+    }
 
-        // Start peeling back the layers of the buffers.
-        // funcs (subtests) -> vals (args/returns) -> fields -> bytes
+    // Start peeling back the layers of the buffers.
+    // funcs (subtests) -> vals (args/returns) -> fields -> bytes
 
-        let mut results: Vec<Result<(), TestFailure>> = Vec::new();
+    let mut results: Vec<Result<(), TestFailure>> = Vec::new();
 
-        // Layer 1 is the funcs/subtests. Because we have already checked
-        // that they agree on their lengths, we can zip them together
-        // to walk through their views of each subtest's execution.
-        'funcs: for (
-            func_idx,
-            (((caller_inputs, caller_outputs), callee_inputs), callee_outputs),
-        ) in caller_inputs
+    // Layer 1 is the funcs/subtests. Because we have already checked
+    // that they agree on their lengths, we can zip them together
+    // to walk through their views of each subtest's execution.
+    'funcs: for (func_idx, (((caller_inputs, caller_outputs), callee_inputs), callee_outputs)) in
+        caller_inputs
             .funcs
             .into_iter()
             .zip(caller_outputs.funcs)
             .zip(callee_inputs.funcs)
             .zip(callee_outputs.funcs)
             .enumerate()
+    {
+        // Now we must enforce that the caller and callee agree on how
+        // many inputs and outputs there were. If this fails that's a
+        // very fundamental issue, and indicative of a bad test generator.
+        if caller_inputs.len() != callee_inputs.len() {
+            results.push(Err(TestFailure::InputCountMismatch(
+                func_idx,
+                caller_inputs,
+                callee_inputs,
+            )));
+            continue 'funcs;
+        }
+        if caller_outputs.len() != callee_outputs.len() {
+            results.push(Err(TestFailure::OutputCountMismatch(
+                func_idx,
+                caller_outputs,
+                callee_outputs,
+            )));
+            continue 'funcs;
+        }
+
+        // Layer 2 is the values (arguments/returns).
+        // The inputs and outputs loop do basically the same work,
+        // but are separate for the sake of error-reporting quality.
+
+        // Process Inputs
+        for (input_idx, (caller_val, callee_val)) in
+            caller_inputs.into_iter().zip(callee_inputs).enumerate()
         {
             // Now we must enforce that the caller and callee agree on how
-            // many inputs and outputs there were. If this fails that's a
-            // very fundamental issue, and indicative of a bad test generator.
-            if caller_inputs.len() != callee_inputs.len() {
-                results.push(Err(TestFailure::InputCountMismatch(
-                    func_idx,
-                    caller_inputs,
-                    callee_inputs,
-                )));
-                continue 'funcs;
-            }
-            if caller_outputs.len() != callee_outputs.len() {
-                results.push(Err(TestFailure::OutputCountMismatch(
-                    func_idx,
-                    caller_outputs,
-                    callee_outputs,
+            // many fields each value had.
+            if caller_val.len() != callee_val.len() {
+                results.push(Err(TestFailure::InputFieldCountMismatch(
+                    func_idx, input_idx, caller_val, callee_val,
                 )));
                 continue 'funcs;
             }
 
-            // Layer 2 is the values (arguments/returns).
-            // The inputs and outputs loop do basically the same work,
-            // but are separate for the sake of error-reporting quality.
-
-            // Process Inputs
-            for (input_idx, (caller_val, callee_val)) in
-                caller_inputs.into_iter().zip(callee_inputs).enumerate()
+            // Layer 3 is the leaf subfields of the values.
+            // At this point we just need to assert that they agree on the bytes.
+            for (field_idx, (caller_field, callee_field)) in
+                caller_val.into_iter().zip(callee_val).enumerate()
             {
-                // Now we must enforce that the caller and callee agree on how
-                // many fields each value had.
-                if caller_val.len() != callee_val.len() {
-                    results.push(Err(TestFailure::InputFieldCountMismatch(
-                        func_idx, input_idx, caller_val, callee_val,
+                // Compilers disagreeing on how big an enum is deserves
+                // a more specific diagnostic than "the bytes are wrong".
+                let is_enum = test
+                    .funcs
+                    .get(func_idx)
+                    .and_then(|func| func.all_inputs().nth(input_idx))
+                    .and_then(|val| val.leaf_fields().get(field_idx).copied())
+                    .is_some_and(|val| matches!(val, Val::Enum(..)));
+                if is_enum && caller_field.len() != callee_field.len() {
+                    results.push(Err(TestFailure::InputEnumSizeMismatch(
+                        func_idx,
+                        input_idx,
+                        field_idx,
+                        caller_field.len(),
+                        callee_field.len(),
                     )));
                     continue 'funcs;
                 }
-
-                // Layer 3 is the leaf subfields of the values.
-                // At this point we just need to assert that they agree on the bytes.
-                for (field_idx, (caller_field, callee_field)) in
-                    caller_val.into_iter().zip(callee_val).enumerate()
-                {
-                    if caller_field != callee_field {
-                        results.push(Err(TestFailure::InputFieldMismatch(
-                            func_idx,
-                            input_idx,
-                            field_idx,
-                            caller_field,
-                            callee_field,
-                        )));
-                        continue 'funcs;
-                    }
+                if caller_field != callee_field {
+                    results.push(Err(TestFailure::InputFieldMismatch(
+                        func_idx,
+                        input_idx,
+                        field_idx,
+                        caller_field,
+                        callee_field,
+                    )));
+                    continue 'funcs;
                 }
             }
+        }
 
-            // Process Outputs
-            for (output_idx, (caller_val, callee_val)) in
-                caller_outputs.into_iter().zip(callee_outputs).enumerate()
+        // Process Outputs
+        for (output_idx, (caller_val, callee_val)) in
+            caller_outputs.into_iter().zip(callee_outputs).enumerate()
+        {
+            // Now we must enforce that the caller and callee agree on how
+            // many fields each value had.
+            if caller_val.len() != callee_val.len() {
+                results.push(Err(TestFailure::OutputFieldCountMismatch(
+                    func_idx, output_idx, caller_val, callee_val,
+                )));
+                continue 'funcs;
+            }
+
+            // Layer 3 is the leaf subfields of the values.
+            // At this point we just need to assert that they agree on the bytes.
+            for (field_idx, (caller_field, callee_field)) in
+                caller_val.into_iter().zip(callee_val).enumerate()
             {
-                // Now we must enforce that the caller and callee agree on how
-                // many fields each value had.
-                if caller_val.len() != callee_val.len() {
-                    results.push(Err(TestFailure::OutputFieldCountMismatch(
-                        func_idx, output_idx, caller_val, callee_val,
+                let is_enum = test
+                    .funcs
+                    .get(func_idx)
+                    .and_then(|func| func.output.as_ref())
+                    .and_then(|val| val.leaf_fields().get(field_idx).copied())
+                    .is_some_and(|val| matches!(val, Val::Enum(..)));
+                if is_enum && caller_field.len() != callee_field.len() {
+                    results.push(Err(TestFailure::OutputEnumSizeMismatch(
+                        func_idx,
+                        output_idx,
+                        field_idx,
+                        caller_field.len(),
+                        callee_field.len(),
                     )));
                     continue 'funcs;
                 }
-
-                // Layer 3 is the leaf subfields of the values.
-                // At this point we just need to assert that they agree on the bytes.
-                for (field_idx, (caller_field, callee_field)) in
-                    caller_val.into_iter().zip(callee_val).enumerate()
-                {
-                    if caller_field != callee_field {
-                        results.push(Err(TestFailure::OutputFieldMismatch(
-                            func_idx,
-                            output_idx,
-                            field_idx,
-                            caller_field,
-                            callee_field,
-                        )));
-                        continue 'funcs;
-                    }
+                if caller_field != callee_field {
+                    results.push(Err(TestFailure::OutputFieldMismatch(
+                        func_idx,
+                        output_idx,
+                        field_idx,
+                        caller_field,
+                        callee_field,
+                    )));
+                    continue 'funcs;
                 }
             }
+        }
+
+        // If we got this far then the test passes
+        results.push(Ok(()));
+    }
 
-            // If we got this far then the test passes
-            results.push(Ok(()));
+    // Blame the test that was running when the harness died, and note
+    // that everything after it never got a chance.
+    if let Some(interruption) = interrupted {
+        let func_idx = results.len();
+        results.push(Err(match interruption {
+            Interruption::Crashed(status) => TestFailure::Crashed(func_idx, status),
+            Interruption::TimedOut(timeout) => TestFailure::TimedOut(func_idx, timeout),
+            Interruption::Aborted(status) => TestFailure::Aborted(func_idx, status),
+        }));
+        for func_idx in func_idx + 1..test.funcs.len() {
+            results.push(Err(TestFailure::NotRun(func_idx)));
         }
+    }
 
-        // Report the results of each subtest
-        //
-        // This will be done again after all tests have been run, but it's
-        // useful to keep a version of this near the actual compilation/execution
-        // in case the compilers spit anything interesting to stdout/stderr.
-        let test_name = &test.name;
-        let names = test
-            .funcs
-            .iter()
-            .map(|test_func| {
-                full_subtest_name(
-                    test_name,
-                    convention_name,
-                    caller_name,
-                    callee_name,
-                    &test_func.name,
-                )
-            })
-            .collect::<Vec<_>>();
-        let max_name_len = names.iter().fold(0, |max, name| max.max(name.len()));
-        let num_passed = results.iter().filter(|r| r.is_ok()).count();
-        let all_passed = num_passed == results.len();
-
-        for (subtest_name, result) in names.iter().zip(&results) {
-            match result {
-                Ok(()) => {
-                    eprintln!("Test {subtest_name:width$} passed", width = max_name_len);
-                }
-                Err(e) => {
-                    eprintln!("Test {subtest_name:width$} failed!", width = max_name_len);
-                    eprintln!("{}", e);
-                }
+    // Report the results of each subtest
+    //
+    // This will be done again after all tests have been run, but it's
+    // useful to keep a version of this near the actual compilation/execution
+    // in case the compilers spit anything interesting to stdout/stderr.
+    let test_name = &test.name;
+    let names = test
+        .funcs
+        .iter()
+        .map(|test_func| {
+            full_subtest_name(
+                test_name,
+                convention_name,
+                caller_name,
+                callee_name,
+                &test_func.name,
+            )
+        })
+        .collect::<Vec<_>>();
+    let max_name_len = names.iter().fold(0, |max, name| max.max(name.len()));
+    let num_passed = results.iter().filter(|r| r.is_ok()).count();
+    let all_passed = num_passed == results.len();
+
+    for (subtest_name, result) in names.iter().zip(&results) {
+        match result {
+            Ok(()) => {
+                eprintln!("Test {subtest_name:width$} passed", width = max_name_len);
+            }
+            Err(e) => {
+                eprintln!("Test {subtest_name:width$} failed!", width = max_name_len);
+                eprintln!("{}", e);
             }
         }
+    }
 
-        if all_passed {
-            eprintln!("all tests passed");
-        } else {
-            eprintln!("only {}/{} tests passed!", num_passed, results.len());
-        }
-        eprintln!();
+    if all_passed {
+        eprintln!("all tests passed");
+    } else {
+        eprintln!("only {}/{} tests passed!", num_passed, results.len());
+    }
+    eprintln!();
+
+    Ok(TestReport {
+        test: test.clone(),
+        results,
+    })
+}
 
-        Ok(TestReport {
-            test: test.clone(),
-            results,
+/// Load the harness dylib and run it inside this process.
+fn run_in_process(dylib: &str) -> Result<TestRun, BuildError> {
+    unsafe {
+        // Initialize all the buffers the tests will write to
+        let mut caller_inputs = WriteBuffer::new();
+        let mut caller_outputs = WriteBuffer::new();
+        let mut callee_inputs = WriteBuffer::new();
+        let mut callee_outputs = WriteBuffer::new();
+
+        // Load the dylib of the test, and get its test_start symbol
+        let lib = libloading::Library::new(dylib)?;
+        let do_test: libloading::Symbol<TestInit> = lib.get(b"test_start")?;
+
+        do_test(
+            write_field,
+            finished_val,
+            finished_func,
+            &mut caller_inputs,
+            &mut caller_outputs,
+            &mut callee_inputs,
+            &mut callee_outputs,
+        );
+
+        Ok(TestRun {
+            caller_inputs,
+            caller_outputs,
+            callee_inputs,
+            callee_outputs,
+            interrupted: None,
         })
     }
 }
 
+/// Whether the harness died by calling abort(), which is what the Rust
+/// runtime does when an unwind can't be caught (or shouldn't have happened).
+#[cfg(unix)]
+fn is_abort(status: &std::process::ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal() == Some(6)
+}
+
+/// Whether the harness died by calling abort(), which is what the Rust
+/// runtime does when an unwind can't be caught (or shouldn't have happened).
+#[cfg(not(unix))]
+fn is_abort(status: &std::process::ExitStatus) -> bool {
+    status.code() == Some(3)
+}
+
+/// Run the harness executable in a child process, killing it if it runs
+/// for longer than `timeout`.
+///
+/// The harness logs every WRITE/FINISHED_VAL/FINISHED_FUNC to a file as it
+/// happens (see harness.rs for the format), which we replay into WriteBuffers
+/// once it's gone. That way a crash or hang still tells us which test did it.
+fn run_out_of_process(
+    exe: &str,
+    target_runner: &[String],
+    timeout: Duration,
+) -> Result<TestRun, BuildError> {
+    let events_path = format!("{exe}.events");
+    // Don't let a harness that dies before making the file inherit the
+    // events of whatever ran before it.
+    match std::fs::remove_file(&events_path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        result => result?,
+    }
+    let mut cmd = match target_runner.split_first() {
+        Some((runner, runner_args)) => {
+            let mut cmd = Command::new(runner);
+            cmd.args(runner_args).arg(exe);
+            cmd
+        }
+        None => Command::new(exe),
+    };
+    let start = Instant::now();
+    let mut child = cmd.arg(&events_path).spawn()?;
+    let interrupted = loop {
+        if let Some(status) = child.try_wait()? {
+            if status.success() {
+                break None;
+            } else if is_abort(&status) {
+                break Some(Interruption::Aborted(status.to_string()));
+            } else {
+                break Some(Interruption::Crashed(status.to_string()));
+            }
+        }
+        if start.elapsed() > timeout {
+            child.kill()?;
+            child.wait()?;
+            break Some(Interruption::TimedOut(timeout));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+
+    // A harness that died early enough might not have even made the file.
+    let events = match std::fs::read_to_string(&events_path) {
+        Err(e) if interrupted.is_some() && e.kind() == std::io::ErrorKind::NotFound => {
+            String::new()
+        }
+        events => events?,
+    };
+
+    // Buffers are numbered in the order test_start takes them.
+    let mut buffers = [
+        WriteBuffer::new(),
+        WriteBuffer::new(),
+        WriteBuffer::new(),
+        WriteBuffer::new(),
+    ];
+    for event in events.lines() {
+        let bad_event = || BuildError::HarnessProtocol(format!("unknown event: {event}"));
+        let parts = event.split(' ').collect::<Vec<_>>();
+        let buffer_idx = |part: usize| {
+            parts
+                .get(part)
+                .and_then(|idx| idx.parse::<usize>().ok())
+                .filter(|&idx| idx < 4)
+                .ok_or_else(bad_event)
+        };
+        match parts[0] {
+            "W" => {
+                let hex = parts.get(2).ok_or_else(bad_event)?;
+                let data = parse_hex(hex).ok_or_else(bad_event)?;
+                buffers[buffer_idx(1)?].write_field(&data);
+            }
+            "V" => buffers[buffer_idx(1)?].finished_val(),
+            "F" => {
+                buffers[buffer_idx(1)?].finished_func();
+                buffers[buffer_idx(2)?].finished_func();
+            }
+            _ => return Err(bad_event()),
+        }
+    }
+
+    let [caller_inputs, caller_outputs, callee_inputs, callee_outputs] = buffers;
+    Ok(TestRun {
+        caller_inputs,
+        caller_outputs,
+        callee_inputs,
+        callee_outputs,
+        interrupted,
+    })
+}
+
+/// Parse a string of hex digit pairs into bytes.
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
 /// The name of a test for pretty-printing.
 fn full_test_name(
     test_name: &str,
@@ -896,12 +1340,99 @@ fn procgen_tests(regenerate: bool) {
                 Val::Int(IntVal::c__uint128(0x1a2b3c4d_23eaf142_7a320c01_e0120a82)),
             ],
         ),
+        // Clang's _BitInt(N), which Rust only has native equivalents of at
+        // power-of-two widths (and _BitInt(128) is aligned like the emulated i128,
+        // not like __int128). Everything else is an opaque blob, like bindgen emits.
+        (
+            "bitint",
+            &[
+                Val::Int(IntVal::c_BitInt {
+                    bits: 24,
+                    signed: true,
+                    value: vec![0x1a2b3c4d_23eaf142],
+                }),
+                Val::Int(IntVal::c_BitInt {
+                    bits: 48,
+                    signed: false,
+                    value: vec![0x1a2b3c4d_23eaf142],
+                }),
+                Val::Int(IntVal::c_BitInt {
+                    bits: 65,
+                    signed: true,
+                    value: vec![0x1a2b3c4d_23eaf142, 0x7a320c01_e0120a82],
+                }),
+                Val::Int(IntVal::c_BitInt {
+                    bits: 128,
+                    signed: true,
+                    value: vec![0x1a2b3c4d_23eaf142, 0x7a320c01_e0120a82],
+                }),
+                Val::Int(IntVal::c_BitInt {
+                    bits: 128,
+                    signed: false,
+                    value: vec![0x1a2b3c4d_23eaf142, 0x7a320c01_e0120a82],
+                }),
+                Val::Int(IntVal::c_BitInt {
+                    bits: 256,
+                    signed: false,
+                    value: vec![
+                        0x1a2b3c4d_23eaf142,
+                        0x7a320c01_e0120a82,
+                        0x0f1e2d3c_4b5a6978,
+                        0x8796a5b4_c3d2e1f0,
+                    ],
+                }),
+            ],
+        ),
+        // Complex numbers have their own classification on some ABIs, which
+        // may or may not match the struct of two floats Rust users rely on.
+        (
+            "complex",
+            &[
+                Val::Complex(FloatVal::c_float(-4921.3527), FloatVal::c_float(0.125)),
+                Val::Complex(
+                    FloatVal::c_double(809239021.392),
+                    FloatVal::c_double(-1.0e10),
+                ),
+            ],
+        ),
+        // SIMD vectors get passed in their own registers (if the right target
+        // features are enabled), so they have plenty of room for disagreement.
+        (
+            "m128",
+            &[Val::Vector(VectorVal::c__m128([
+                809.25, -4921.3527, 0.125, 1.0e10,
+            ]))],
+        ),
+        (
+            "m128i",
+            &[Val::Vector(VectorVal::c__m128i([
+                0x1a2b3c4d_23eaf142,
+                0x7a320c01_e0120a82,
+            ]))],
+        ),
+        (
+            "m256",
+            &[Val::Vector(VectorVal::c__m256([
+                809.25, -4921.3527, 0.125, 1.0e10, 3.5, -0.0625, 123.456, 7.0,
+            ]))],
+        ),
+        (
+            "m256d",
+            &[Val::Vector(VectorVal::c__m256d([
+                809239021.392,
+                -4921.3527,
+                0.125,
+                1.0e100,
+            ]))],
+        ),
     ];
 
     for (test_name, vals) in tests {
         let mut test = Test {
             name: test_name.to_string(),
+            types: Vec::new(),
             funcs: Vec::new(),
+            out_of_process: false,
         };
 
         let mut perturb_float = 0.0f32;
@@ -914,12 +1445,24 @@ fn procgen_tests(regenerate: bool) {
                 let mut cur_val = Some(&mut new_val);
                 while let Some(temp) = cur_val.take() {
                     match temp {
-                        Val::Ref(pointee) => {
+                        Val::Ref(pointee, _)
+                        | Val::RustType(_, pointee)
+                        | Val::Transparent(_, pointee) => {
                             cur_val = Some(&mut **pointee);
                             continue;
                         }
                         Val::Struct(_, _) => unimplemented!(),
-                        Val::Array(_) => unimplemented!(),
+                        Val::ReprStruct(..) => unimplemented!(),
+                        Val::Union(_, _, _) => unimplemented!(),
+                        Val::Enum(_, _, _, _) => unimplemented!(),
+                        Val::DataEnum(..) => unimplemented!(),
+                        Val::Array(..) => unimplemented!(),
+                        Val::FnPtr(..) => unimplemented!(),
+                        Val::BitField(..) => unimplemented!(),
+                        Val::CStr(_) | Val::WideCStr(_) => unimplemented!(),
+                        Val::EmptyArray(_) | Val::FlexArray(..) | Val::Atomic(_) => {
+                            unimplemented!()
+                        }
                         Val::Ptr(out) => graffiti_primitive(out, i),
                         Val::Int(int_val) => match int_val {
                             IntVal::c__int128(out) => graffiti_primitive(out, i),
@@ -928,14 +1471,53 @@ fn procgen_tests(regenerate: bool) {
                             IntVal::c_int16_t(out) => graffiti_primitive(out, i),
                             IntVal::c_int8_t(out) => graffiti_primitive(out, i),
                             IntVal::c__uint128(out) => graffiti_primitive(out, i),
+                            IntVal::c_BitInt { value, .. } => {
+                                for (limb, out) in value.iter_mut().enumerate() {
+                                    graffiti_primitive(out, (i + limb) % 16)
+                                }
+                            }
                             IntVal::c_uint64_t(out) => graffiti_primitive(out, i),
                             IntVal::c_uint32_t(out) => graffiti_primitive(out, i),
                             IntVal::c_uint16_t(out) => graffiti_primitive(out, i),
                             IntVal::c_uint8_t(out) => graffiti_primitive(out, i),
+                            IntVal::c_char(out) => graffiti_primitive(out, i),
+                            IntVal::c_schar(out) => graffiti_primitive(out, i),
+                            IntVal::c_uchar(out) => graffiti_primitive(out, i),
+                            IntVal::c_int(out) => graffiti_primitive(out, i),
+                            IntVal::c_uint(out) => graffiti_primitive(out, i),
+                            IntVal::c_long(out) => graffiti_primitive(out, i),
+                            IntVal::c_ulong(out) => graffiti_primitive(out, i),
+                            IntVal::c_size_t(out) => graffiti_primitive(out, i),
+                            IntVal::c_ptrdiff_t(out) => graffiti_primitive(out, i),
+                            IntVal::c_intptr_t(out) => graffiti_primitive(out, i),
+                            IntVal::c_uintptr_t(out) => graffiti_primitive(out, i),
                         },
                         Val::Float(float_val) => match float_val {
                             FloatVal::c_double(out) => graffiti_primitive(out, i),
                             FloatVal::c_float(out) => graffiti_primitive(out, i),
+                            FloatVal::c_long_double(out) => graffiti_primitive(out, i),
+                            FloatVal::c__float128(out) => graffiti_primitive(out, i),
+                            FloatVal::c_Float16(out) => graffiti_primitive(out, i),
+                            FloatVal::c__bf16(out) => graffiti_primitive(out, i),
+                        },
+                        Val::Complex(re, im) => {
+                            for (part, float_val) in [re, im].into_iter().enumerate() {
+                                let i = (i + part) % 16;
+                                match float_val {
+                                    FloatVal::c_double(out) => graffiti_primitive(out, i),
+                                    FloatVal::c_float(out) => graffiti_primitive(out, i),
+                                    FloatVal::c_long_double(out) => graffiti_primitive(out, i),
+                                    FloatVal::c__float128(out) => graffiti_primitive(out, i),
+                                    FloatVal::c_Float16(out) => graffiti_primitive(out, i),
+                                    FloatVal::c__bf16(out) => graffiti_primitive(out, i),
+                                }
+                            }
+                        }
+                        Val::Vector(vector_val) => match vector_val {
+                            VectorVal::c__m128(out) => graffiti_lanes(out, i),
+                            VectorVal::c__m128i(out) => graffiti_lanes(out, i),
+                            VectorVal::c__m256(out) => graffiti_lanes(out, i),
+                            VectorVal::c__m256d(out) => graffiti_lanes(out, i),
                         },
                         Val::Bool(out) => *out = true,
                     }
@@ -951,43 +1533,55 @@ fn procgen_tests(regenerate: bool) {
                 name: format!("{val_name}_val_in"),
                 conventions: vec![CallingConvention::All],
                 inputs: vec![new_val(0)],
+                variadic_inputs: vec![],
                 output: None,
+                callee_unwinds: false,
             });
 
             test.funcs.push(Func {
                 name: format!("{val_name}_val_out"),
                 conventions: vec![CallingConvention::All],
                 inputs: vec![],
+                variadic_inputs: vec![],
                 output: Some(new_val(0)),
+                callee_unwinds: false,
             });
 
             test.funcs.push(Func {
                 name: format!("{val_name}_val_in_out"),
                 conventions: vec![CallingConvention::All],
                 inputs: vec![new_val(0)],
+                variadic_inputs: vec![],
                 output: Some(new_val(1)),
+                callee_unwinds: false,
             });
 
             // Start gentle with basic one value in/out tests
             test.funcs.push(Func {
                 name: format!("{val_name}_ref_in"),
                 conventions: vec![CallingConvention::All],
-                inputs: vec![Val::Ref(Box::new(new_val(0)))],
+                inputs: vec![Val::Ref(Box::new(new_val(0)), RefFlavor::Default)],
+                variadic_inputs: vec![],
                 output: None,
+                callee_unwinds: false,
             });
 
             test.funcs.push(Func {
                 name: format!("{val_name}_ref_out"),
                 conventions: vec![CallingConvention::All],
                 inputs: vec![],
-                output: Some(Val::Ref(Box::new(new_val(0)))),
+                variadic_inputs: vec![],
+                output: Some(Val::Ref(Box::new(new_val(0)), RefFlavor::Default)),
+                callee_unwinds: false,
             });
 
             test.funcs.push(Func {
                 name: format!("{val_name}_ref_in_out"),
                 conventions: vec![CallingConvention::All],
-                inputs: vec![Val::Ref(Box::new(new_val(0)))],
-                output: Some(Val::Ref(Box::new(new_val(1)))),
+                inputs: vec![Val::Ref(Box::new(new_val(0)), RefFlavor::Default)],
+                variadic_inputs: vec![],
+                output: Some(Val::Ref(Box::new(new_val(1)), RefFlavor::Default)),
+                callee_unwinds: false,
             });
 
             // Stress out the calling convention and try lots of different
@@ -998,7 +1592,9 @@ fn procgen_tests(regenerate: bool) {
                     name: format!("{val_name}_val_in_{len}"),
                     conventions: vec![CallingConvention::All],
                     inputs: (0..len).map(|i| new_val(i)).collect(),
+                    variadic_inputs: vec![],
                     output: None,
+                    callee_unwinds: false,
                 });
             }
 
@@ -1013,7 +1609,9 @@ fn procgen_tests(regenerate: bool) {
                         format!("{val_name}_{len}"),
                         (0..len).map(|i| new_val(i)).collect(),
                     )],
+                    variadic_inputs: vec![],
                     output: None,
+                    callee_unwinds: false,
                 });
             }
             // Check that by-ref works, for good measure
@@ -1021,11 +1619,16 @@ fn procgen_tests(regenerate: bool) {
                 test.funcs.push(Func {
                     name: format!("{val_name}_ref_struct_in_{len}"),
                     conventions: vec![CallingConvention::All],
-                    inputs: vec![Val::Ref(Box::new(Val::Struct(
-                        format!("{val_name}_{len}"),
-                        (0..len).map(|i| new_val(i)).collect(),
-                    )))],
+                    inputs: vec![Val::Ref(
+                        Box::new(Val::Struct(
+                            format!("{val_name}_{len}"),
+                            (0..len).map(|i| new_val(i)).collect(),
+                        )),
+                        RefFlavor::Default,
+                    )],
+                    variadic_inputs: vec![],
                     output: None,
+                    callee_unwinds: false,
                 });
             }
 
@@ -1054,7 +1657,9 @@ fn procgen_tests(regenerate: bool) {
                     name: format!("{val_name}_val_in_{idx}_perturbed_small"),
                     conventions: vec![CallingConvention::All],
                     inputs: inputs,
+                    variadic_inputs: vec![],
                     output: None,
+                    callee_unwinds: false,
                 });
             }
             for idx in 0..big_count {
@@ -1071,7 +1676,9 @@ fn procgen_tests(regenerate: bool) {
                     name: format!("{val_name}_val_in_{idx}_perturbed_big"),
                     conventions: vec![CallingConvention::All],
                     inputs: inputs,
+                    variadic_inputs: vec![],
                     output: None,
+                    callee_unwinds: false,
                 });
             }
 
@@ -1092,7 +1699,9 @@ fn procgen_tests(regenerate: bool) {
                         format!("{val_name}_{idx}_perturbed_small"),
                         inputs,
                     )],
+                    variadic_inputs: vec![],
                     output: None,
+                    callee_unwinds: false,
                 });
             }
             for idx in 0..big_count {
@@ -1112,7 +1721,9 @@ fn procgen_tests(regenerate: bool) {
                         format!("{val_name}_{idx}_perturbed_big"),
                         inputs,
                     )],
+                    variadic_inputs: vec![],
                     output: None,
+                    callee_unwinds: false,
                 });
             }
 
@@ -1130,11 +1741,16 @@ fn procgen_tests(regenerate: bool) {
                 test.funcs.push(Func {
                     name: format!("{val_name}_ref_struct_in_{idx}_perturbed_small"),
                     conventions: vec![CallingConvention::All],
-                    inputs: vec![Val::Ref(Box::new(Val::Struct(
-                        format!("{val_name}_{idx}_perturbed_small"),
-                        inputs,
-                    )))],
+                    inputs: vec![Val::Ref(
+                        Box::new(Val::Struct(
+                            format!("{val_name}_{idx}_perturbed_small"),
+                            inputs,
+                        )),
+                        RefFlavor::Default,
+                    )],
+                    variadic_inputs: vec![],
                     output: None,
+                    callee_unwinds: false,
                 });
             }
             for idx in 0..big_count {
@@ -1150,11 +1766,16 @@ fn procgen_tests(regenerate: bool) {
                 test.funcs.push(Func {
                     name: format!("{val_name}_ref_struct_in_{idx}_perturbed_big"),
                     conventions: vec![CallingConvention::All],
-                    inputs: vec![Val::Ref(Box::new(Val::Struct(
-                        format!("{val_name}_{idx}_perturbed_big"),
-                        inputs,
-                    )))],
+                    inputs: vec![Val::Ref(
+                        Box::new(Val::Struct(
+                            format!("{val_name}_{idx}_perturbed_big"),
+                            inputs,
+                        )),
+                        RefFlavor::Default,
+                    )],
+                    variadic_inputs: vec![],
                     output: None,
+                    callee_unwinds: false,
                 });
             }
         }
@@ -1170,17 +1791,38 @@ pub fn arg_ty(val: &Val) -> String {
     use IntVal::*;
     use Val::*;
     match val {
-        Ref(x) => format!("ref_{}", arg_ty(x)),
+        Ref(x, _) => format!("ref_{}", arg_ty(x)),
+        RustType(_, x) => arg_ty(x),
+        Transparent(name, _) => format!("transparent_{name}"),
         Ptr(_) => format!("ptr"),
+        EmptyArray(elem_ty) => format!("arr_0_{}", arg_ty(elem_ty)),
+        FlexArray(elem_ty, _) => format!("flex_{}", arg_ty(elem_ty)),
+        Atomic(int_val) => format!("atomic_{}", arg_ty(&Int(int_val.clone()))),
+        CStr(_) => format!("cstr"),
+        WideCStr(_) => format!("wide_cstr"),
         Bool(_) => format!("bool"),
-        Array(vals) => format!(
-            "arr_{}_{}",
-            vals.len(),
-            arg_ty(vals.get(0).expect("arrays must have length > 0")),
-        ),
-        Struct(name, _) => format!("struct_{name}"),
+        Array(elem_ty, vals) => format!("arr_{}_{}", vals.len(), arg_ty(elem_ty)),
+        Struct(name, _) | ReprStruct(name, _, _) => format!("struct_{name}"),
+        Union(name, _, _) => format!("union_{name}"),
+        Enum(name, _, _, _) => format!("enum_{name}"),
+        DataEnum(name, _, _, _) => format!("data_enum_{name}"),
+        FnPtr(name, _, _) => format!("fn_{name}"),
+        BitField(width, int_val) => format!("bits{width}_{}", arg_ty(&Int(int_val.clone()))),
+        Vector(vector_val) => match vector_val {
+            VectorVal::c__m128(_) => format!("m128"),
+            VectorVal::c__m128i(_) => format!("m128i"),
+            VectorVal::c__m256(_) => format!("m256"),
+            VectorVal::c__m256d(_) => format!("m256d"),
+        },
         Float(FloatVal::c_double(_)) => format!("f64"),
         Float(FloatVal::c_float(_)) => format!("f32"),
+        Float(FloatVal::c_long_double(_)) => format!("long_double"),
+        Float(FloatVal::c__float128(_)) => format!("f128"),
+        Float(FloatVal::c_Float16(_)) => format!("f16"),
+        Float(FloatVal::c__bf16(_)) => format!("bf16"),
+        // Mixed-part complex numbers are rejected by the backends, so the
+        // real part's type is the whole thing's
+        Complex(re, _) => format!("complex_{}", arg_ty(&Float(re.clone()))),
         Int(int_val) => match int_val {
             c__int128(_) => format!("i128"),
             c_int64_t(_) => format!("i64"),
@@ -1188,14 +1830,41 @@ pub fn arg_ty(val: &Val) -> String {
             c_int16_t(_) => format!("i16"),
             c_int8_t(_) => format!("i8"),
             c__uint128(_) => format!("u128"),
+            c_BitInt { bits, signed, .. } => {
+                if *signed {
+                    format!("bitint{bits}")
+                } else {
+                    format!("ubitint{bits}")
+                }
+            }
             c_uint64_t(_) => format!("u64"),
             c_uint32_t(_) => format!("u32"),
             c_uint16_t(_) => format!("u16"),
             c_uint8_t(_) => format!("u8"),
+            c_char(_) => format!("char"),
+            c_schar(_) => format!("schar"),
+            c_uchar(_) => format!("uchar"),
+            c_int(_) => format!("int"),
+            c_uint(_) => format!("uint"),
+            c_long(_) => format!("long"),
+            c_ulong(_) => format!("ulong"),
+            c_size_t(_) => format!("size_t"),
+            c_ptrdiff_t(_) => format!("ptrdiff_t"),
+            c_intptr_t(_) => format!("intptr_t"),
+            c_uintptr_t(_) => format!("uintptr_t"),
         },
     }
 }
 
+/// Graffiti each lane of a vector with a different pattern, so that lanes
+/// getting shuffled around is noticed. (Graffiti-ing the whole vector in one
+/// go would also produce float lanes that are NaN, which we can't write out.)
+fn graffiti_lanes<T>(lanes: &mut [T], idx: usize) {
+    for (lane_idx, lane) in lanes.iter_mut().enumerate() {
+        graffiti_primitive(lane, (idx + lane_idx) % 16);
+    }
+}
+
 fn graffiti_primitive<T>(output: &mut T, idx: usize) {
     let mut input = [
         0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E,
@@ -0,0 +1,334 @@
+//! Machine-readable versions of the final results, for dashboards and CI.
+//!
+//! Everything is first boiled down into a [`RunReport`], which is what
+//! --report-json serializes, and what --report-junit is generated from.
+
+use crate::abis::{Target, ARG_NAMES, OUTPUT_NAME};
+use crate::{built_info, full_test_name, TestFailure, TestOutcome};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// The results of every test that was run (skipped tests aren't included).
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub abi_checker_version: &'static str,
+    pub rustc_version: &'static str,
+    /// The compiler (and its version) that each C impl in the tests ran.
+    pub c_compilers: BTreeMap<&'static str, String>,
+    pub host: &'static str,
+    pub target: String,
+    pub tests: Vec<TestEntry>,
+}
+
+/// The results of one test under one convention for one "X calls Y" pair.
+#[derive(Debug, Serialize)]
+pub struct TestEntry {
+    pub name: String,
+    pub test: String,
+    pub convention: &'static str,
+    pub caller: &'static str,
+    pub callee: &'static str,
+    /// Set if the test couldn't be generated/built/run at all, in which
+    /// case there are no subtest results.
+    pub error: Option<String>,
+    pub subtests: Vec<SubtestEntry>,
+}
+
+/// The result of one function in a test.
+#[derive(Debug, Serialize)]
+pub struct SubtestEntry {
+    pub name: String,
+    pub passed: bool,
+    pub failure: Option<FailureEntry>,
+}
+
+/// The details of a failed subtest.
+#[derive(Debug, Serialize)]
+pub struct FailureEntry {
+    /// The name of the TestFailure variant.
+    pub kind: &'static str,
+    /// The same message that gets printed to stderr.
+    pub message: String,
+    /// The value (arg/output) the two sides disagreed on, if we got that far.
+    pub val: Option<&'static str>,
+    /// The leaf field of `val` the two sides disagreed on, if we got that far.
+    pub field: Option<usize>,
+    /// The bytes the caller reported for the thing they disagreed on.
+    /// This is a list of bytes for a field, a list of fields for a value,
+    /// a list of values for an input/output count mismatch, and just the
+    /// size of the enum for an enum size mismatch.
+    pub caller: Option<serde_json::Value>,
+    /// The bytes the callee reported for the thing they disagreed on.
+    pub callee: Option<serde_json::Value>,
+}
+
+impl RunReport {
+    pub fn new(
+        reports: &[TestOutcome],
+        target: &Target,
+        c_compilers: &BTreeMap<&'static str, String>,
+    ) -> Self {
+        let tests = reports
+            .iter()
+            .map(
+                |(test_name, convention, caller_name, callee_name, report)| {
+                    let convention_name = convention.name();
+                    let mut entry = TestEntry {
+                        name: full_test_name(test_name, convention_name, caller_name, callee_name),
+                        test: test_name.clone(),
+                        convention: convention_name,
+                        caller: caller_name,
+                        callee: callee_name,
+                        error: None,
+                        subtests: Vec::new(),
+                    };
+                    match report {
+                        Err(e) => entry.error = Some(e.to_string()),
+                        Ok(report) => {
+                            for (func, result) in report.test.funcs.iter().zip(&report.results) {
+                                entry.subtests.push(SubtestEntry {
+                                    name: func.name.clone(),
+                                    passed: result.is_ok(),
+                                    failure: result.as_ref().err().map(FailureEntry::new),
+                                });
+                            }
+                        }
+                    }
+                    entry
+                },
+            )
+            .collect();
+
+        let c_compilers = c_compilers
+            .iter()
+            .filter(|(name, _)| {
+                reports
+                    .iter()
+                    .any(|(_, _, caller, callee, _)| caller == *name || callee == *name)
+            })
+            .map(|(name, compiler)| (*name, compiler.clone()))
+            .collect();
+
+        RunReport {
+            abi_checker_version: built_info::PKG_VERSION,
+            rustc_version: built_info::RUSTC_VERSION,
+            c_compilers,
+            host: built_info::HOST,
+            target: target.triple.clone(),
+            tests,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Render the report as JUnit XML.
+    ///
+    /// Each TestEntry becomes a testsuite, and each subtest a testcase.
+    /// Crashes, aborts, and hangs are errors, subtests that never got to run
+    /// are skipped, and everything else that went wrong is a failure.
+    pub fn to_junit(&self) -> String {
+        let mut out = String::new();
+        let total = self
+            .tests
+            .iter()
+            .map(|t| t.subtests.len().max(1))
+            .sum::<usize>();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(out, r#"<testsuites name="abi-checker" tests="{total}">"#).unwrap();
+        for test in &self.tests {
+            let name = xml_escape(&test.name);
+            let failures = test
+                .subtests
+                .iter()
+                .filter(|s| matches!(&s.failure, Some(f) if !is_junit_error(f.kind) && f.kind != "NotRun"))
+                .count();
+            let errors = test
+                .subtests
+                .iter()
+                .filter(|s| matches!(&s.failure, Some(f) if is_junit_error(f.kind)))
+                .count()
+                + test.error.is_some() as usize;
+            let skipped = test
+                .subtests
+                .iter()
+                .filter(|s| matches!(&s.failure, Some(f) if f.kind == "NotRun"))
+                .count();
+            let tests = test.subtests.len().max(1);
+            writeln!(
+                out,
+                r#"  <testsuite name="{name}" tests="{tests}" failures="{failures}" errors="{errors}" skipped="{skipped}">"#
+            )
+            .unwrap();
+            writeln!(out, "    <properties>").unwrap();
+            for (key, val) in [
+                ("convention", test.convention),
+                ("caller", test.caller),
+                ("callee", test.callee),
+                ("rustc_version", self.rustc_version),
+                ("target", &self.target),
+            ] {
+                let val = xml_escape(val);
+                writeln!(out, r#"      <property name="{key}" value="{val}"/>"#).unwrap();
+            }
+            for side in [test.caller, test.callee] {
+                if let Some(compiler) = self.c_compilers.get(side) {
+                    let compiler = xml_escape(compiler);
+                    writeln!(
+                        out,
+                        r#"      <property name="{side}_version" value="{compiler}"/>"#
+                    )
+                    .unwrap();
+                }
+            }
+            writeln!(out, "    </properties>").unwrap();
+
+            if let Some(error) = &test.error {
+                // There's no subtests to speak of, so just make one up
+                // for the whole test to report the error on.
+                writeln!(out, r#"    <testcase name="{name}" classname="{name}">"#).unwrap();
+                writeln!(
+                    out,
+                    r#"      <error message="test couldn't be built or run">{}</error>"#,
+                    xml_escape(error)
+                )
+                .unwrap();
+                writeln!(out, "    </testcase>").unwrap();
+            }
+            for subtest in &test.subtests {
+                let subtest_name = xml_escape(&subtest.name);
+                match &subtest.failure {
+                    None => {
+                        writeln!(
+                            out,
+                            r#"    <testcase name="{subtest_name}" classname="{name}"/>"#
+                        )
+                        .unwrap();
+                    }
+                    Some(failure) => {
+                        let tag = if failure.kind == "NotRun" {
+                            "skipped"
+                        } else if is_junit_error(failure.kind) {
+                            "error"
+                        } else {
+                            "failure"
+                        };
+                        let message =
+                            xml_escape(failure.message.lines().next().unwrap_or("").trim_end());
+                        writeln!(
+                            out,
+                            r#"    <testcase name="{subtest_name}" classname="{name}">"#
+                        )
+                        .unwrap();
+                        writeln!(
+                            out,
+                            r#"      <{tag} message="{message}" type="{}">{}</{tag}>"#,
+                            failure.kind,
+                            xml_escape(&failure.message)
+                        )
+                        .unwrap();
+                        writeln!(out, "    </testcase>").unwrap();
+                    }
+                }
+            }
+            writeln!(out, "  </testsuite>").unwrap();
+        }
+        writeln!(out, "</testsuites>").unwrap();
+        out
+    }
+}
+
+impl FailureEntry {
+    fn new(failure: &TestFailure) -> Self {
+        use TestFailure::*;
+        let mut entry = FailureEntry {
+            kind: "",
+            message: failure.to_string(),
+            val: None,
+            field: None,
+            caller: None,
+            callee: None,
+        };
+        match failure {
+            InputFieldMismatch(_, val_idx, field_idx, caller, callee) => {
+                entry.kind = "InputFieldMismatch";
+                entry.val = Some(ARG_NAMES[*val_idx]);
+                entry.field = Some(*field_idx);
+                entry.caller = Some(json!(caller));
+                entry.callee = Some(json!(callee));
+            }
+            OutputFieldMismatch(_, _, field_idx, caller, callee) => {
+                entry.kind = "OutputFieldMismatch";
+                entry.val = Some(OUTPUT_NAME);
+                entry.field = Some(*field_idx);
+                entry.caller = Some(json!(caller));
+                entry.callee = Some(json!(callee));
+            }
+            InputFieldCountMismatch(_, val_idx, caller, callee) => {
+                entry.kind = "InputFieldCountMismatch";
+                entry.val = Some(ARG_NAMES[*val_idx]);
+                entry.caller = Some(json!(caller));
+                entry.callee = Some(json!(callee));
+            }
+            OutputFieldCountMismatch(_, _, caller, callee) => {
+                entry.kind = "OutputFieldCountMismatch";
+                entry.val = Some(OUTPUT_NAME);
+                entry.caller = Some(json!(caller));
+                entry.callee = Some(json!(callee));
+            }
+            InputEnumSizeMismatch(_, val_idx, field_idx, caller, callee) => {
+                entry.kind = "InputEnumSizeMismatch";
+                entry.val = Some(ARG_NAMES[*val_idx]);
+                entry.field = Some(*field_idx);
+                entry.caller = Some(json!(caller));
+                entry.callee = Some(json!(callee));
+            }
+            OutputEnumSizeMismatch(_, _, field_idx, caller, callee) => {
+                entry.kind = "OutputEnumSizeMismatch";
+                entry.val = Some(OUTPUT_NAME);
+                entry.field = Some(*field_idx);
+                entry.caller = Some(json!(caller));
+                entry.callee = Some(json!(callee));
+            }
+            InputCountMismatch(_, caller, callee) => {
+                entry.kind = "InputCountMismatch";
+                entry.caller = Some(json!(caller));
+                entry.callee = Some(json!(callee));
+            }
+            OutputCountMismatch(_, caller, callee) => {
+                entry.kind = "OutputCountMismatch";
+                entry.caller = Some(json!(caller));
+                entry.callee = Some(json!(callee));
+            }
+            Crashed(..) => entry.kind = "Crashed",
+            TimedOut(..) => entry.kind = "TimedOut",
+            Aborted(..) => entry.kind = "Aborted",
+            NotRun(..) => entry.kind = "NotRun",
+        }
+        entry
+    }
+}
+
+/// Whether this kind of failure is the test blowing up, rather than the
+/// two sides disagreeing.
+fn is_junit_error(kind: &str) -> bool {
+    kind == "Crashed" || kind == "TimedOut" || kind == "Aborted"
+}
+
+fn xml_escape(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}